@@ -1,53 +1,44 @@
-use std::{
-    fs::File,
-    io::{Cursor, Read, Write},
-};
+use std::fs::File;
 
-use byteorder::{BigEndian, ReadBytesExt};
 use rflv::{
-    error::FlvError,
     file::FlvFile,
     v1::{
-        audio::{AacAudioData, AudioData, FlvAudioTag},
-        header::{FLV_HEADER_SIGNATURE, FLV_HEADER_VERSION, FlvHeader, HeaderFlags},
+        header::{FlvHeader, HeaderFlags},
         script::{Amf0DataObjectProp, Amf0Key, Amf0String, Amf0Value, FlvScriptTag},
-        tag::{FlvTag, FlvTagData, FlvTagType, calc_previous_tag_size},
-        video::{
-            AvcPacketType, AvcVideoPacket, CodecId, FlvVideoData, FrameType, FrameTypeTest,
-            VideoData,
-        },
+        tag::{FlvTag, FlvTagType},
+        video::{AvcVideoPacket, CodecId, FlvVideoData, FrameType, VideoData},
     },
 };
 
 fn main() {
-    let keyframe: u8 = FrameTypeTest::Keyframe.into();
-    println!("{:?}", keyframe);
-    loop {}
-
     let mut file = File::create("test.flv").unwrap();
     let sequence_header = FlvVideoData {
-        frame_type: FrameType::KEYFRAME,
-        codec: CodecId::AVC,
+        frame_type: FrameType::Keyframe,
+        codec: CodecId::Avc,
         video_data: VideoData::Avc(AvcVideoPacket::new_sequence_header(
             SEQUENCE_HEADER.to_vec(),
         )),
     };
 
-    println!("{:?} {}", sequence_header.size(), SEQUENCE_HEADER.len());
+    println!(
+        "{:?} {}",
+        sequence_header.encoded_len(),
+        SEQUENCE_HEADER.len()
+    );
 
     let frame = FlvVideoData {
-        frame_type: FrameType::KEYFRAME,
-        codec: CodecId::AVC,
+        frame_type: FrameType::Keyframe,
+        codec: CodecId::Avc,
         video_data: VideoData::Avc(AvcVideoPacket::new_nalu(FRAME.to_vec(), 0)),
     };
 
     let eos = FlvVideoData {
-        frame_type: FrameType::KEYFRAME,
-        codec: CodecId::AVC,
+        frame_type: FrameType::Keyframe,
+        codec: CodecId::Avc,
         video_data: VideoData::Avc(AvcVideoPacket::eos()),
     };
 
-    let script = FlvScriptTag::new(
+    let script = FlvScriptTag::from_props(
         "test".to_string(),
         vec![Amf0DataObjectProp {
             name: Amf0Key::new("name".to_string()).unwrap(), // SAFE UNWRAP,
@@ -75,11 +66,11 @@ fn main() {
     let r = FlvFile::decode(&mut file).unwrap();
 
     for tag in r.tags {
-        if tag.tag_type == FlvTagType::SCRIPT_DATA {
+        if tag.tag_type() == FlvTagType::ScriptData {
             println!("{:?}", tag);
         }
         println!("{:?}", tag.data);
-        println!("{:?}", tag.tag_type);
+        println!("{:?}", tag.tag_type());
     }
 }
 
@@ -0,0 +1,719 @@
+use std::{
+    fmt::Display,
+    io::{Read, Write},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{
+        self, DeserializeOwned, EnumAccess, IntoDeserializer, Unexpected, VariantAccess, Visitor,
+        value::{MapDeserializer, SeqDeserializer},
+    },
+    forward_to_deserialize_any, ser,
+};
+
+use crate::v1::script::{
+    Amf0Bool, Amf0DataObjectProp, Amf0Date, Amf0EcmaArray, Amf0Error, Amf0Key, Amf0LongString,
+    Amf0Number, Amf0Object, Amf0StrictArray, Amf0String, Amf0Value,
+};
+
+// serde data model <-> AMF0:
+// bool: Bool, numbers: Number, strings: String (LongString if longer than 65535 bytes),
+// None and unit: Null, sequences and tuples: StrictArray, structs: Object, maps: ECMA array,
+// `Amf0Date`: Date, enums: the name of the variant or an Object with the variant as its only key
+
+/// Name of the newtype `Amf0Date` is serialized as, the serializer writes it as a Date
+const AMF0_DATE_NEWTYPE: &str = "$rflv::amf0::Date";
+
+/// Encodes `value` as one AMF0 value
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(
+    stream: &mut W,
+    value: &T,
+) -> Result<(), Amf0Error> {
+    to_value(value)?.encode(stream)
+}
+
+/// Decodes one AMF0 value as a `T`
+pub fn from_reader<R: Read, T: DeserializeOwned>(stream: &mut R) -> Result<T, Amf0Error> {
+    from_value(Amf0Value::decode(stream)?)
+}
+
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Amf0Value, Amf0Error> {
+    value.serialize(ValueSerializer)
+}
+
+pub fn from_value<T: DeserializeOwned>(value: Amf0Value) -> Result<T, Amf0Error> {
+    T::deserialize(value.into_deserializer())
+}
+
+impl ser::Error for Amf0Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+impl de::Error for Amf0Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self::Custom(msg.to_string())
+    }
+}
+
+/// Written as a Date by `to_writer`, as its milliseconds by other formats
+impl Serialize for Amf0Date {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(AMF0_DATE_NEWTYPE, &self.millis)
+    }
+}
+
+impl<'de> Deserialize<'de> for Amf0Date {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DateVisitor;
+
+        impl<'de> Visitor<'de> for DateVisitor {
+            type Value = Amf0Date;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("an AMF0 date")
+            }
+
+            fn visit_newtype_struct<D: Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<Self::Value, D::Error> {
+                f64::deserialize(deserializer).map(Amf0Date::new)
+            }
+
+            fn visit_f64<E: de::Error>(self, millis: f64) -> Result<Self::Value, E> {
+                Ok(Amf0Date::new(millis))
+            }
+        }
+
+        deserializer.deserialize_newtype_struct(AMF0_DATE_NEWTYPE, DateVisitor)
+    }
+}
+
+/// `SystemTime` as an AMF0 Date, for `#[serde(with = "rflv::amf0::date")]`
+pub mod date {
+    use super::{
+        Amf0Date, Deserialize, Deserializer, Duration, Serialize, Serializer, SystemTime,
+        UNIX_EPOCH, de,
+    };
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let millis = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => millis(since),
+            Err(before) => -millis(before.duration()),
+        };
+
+        Amf0Date::new(millis).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let millis = Amf0Date::deserialize(deserializer)?.millis;
+
+        if !millis.is_finite() {
+            return Err(de::Error::custom("date is not finite"));
+        }
+
+        // whole milliseconds and the rest, so dates far from the epoch keep their precision
+        let offset = Duration::from_millis(millis.abs().trunc() as u64)
+            + Duration::from_nanos((millis.abs().fract() * 1e6).round() as u64);
+
+        let time = if millis >= 0.0 {
+            UNIX_EPOCH.checked_add(offset)
+        } else {
+            UNIX_EPOCH.checked_sub(offset)
+        };
+
+        time.ok_or_else(|| de::Error::custom("date out of range"))
+    }
+
+    fn millis(duration: Duration) -> f64 {
+        duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1e6
+    }
+}
+
+fn string(content: String) -> Result<Amf0Value, Amf0Error> {
+    if content.len() > u16::MAX as usize {
+        return Ok(Amf0Value::LongString(Amf0LongString::new(content)?));
+    }
+
+    Ok(Amf0Value::String(Amf0String::new(content)?))
+}
+
+fn prop(name: String, value: Amf0Value) -> Result<Amf0DataObjectProp, Amf0Error> {
+    Ok(Amf0DataObjectProp {
+        name: Amf0Key::new(name)?,
+        value,
+    })
+}
+
+/// Object with the variant as its only key
+fn variant(name: &'static str, value: Amf0Value) -> Result<Amf0Value, Amf0Error> {
+    Ok(Amf0Value::Object(Amf0Object::new(vec![prop(
+        name.to_string(),
+        value,
+    )?])))
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Amf0Value;
+    type Error = Amf0Error;
+
+    type SerializeSeq = SerializeArray;
+    type SerializeTuple = SerializeArray;
+    type SerializeTupleStruct = SerializeArray;
+    type SerializeTupleVariant = SerializeArray;
+    type SerializeMap = SerializeMap;
+    type SerializeStruct = SerializeObject;
+    type SerializeStructVariant = SerializeObject;
+
+    fn serialize_bool(self, v: bool) -> Result<Amf0Value, Amf0Error> {
+        Ok(Amf0Value::Bool(Amf0Bool::new(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Amf0Value, Amf0Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Amf0Value, Amf0Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Amf0Value, Amf0Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    /// Numbers are doubles, integers over 2^53 lose precision
+    fn serialize_i64(self, v: i64) -> Result<Amf0Value, Amf0Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Amf0Value, Amf0Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Amf0Value, Amf0Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Amf0Value, Amf0Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Amf0Value, Amf0Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Amf0Value, Amf0Error> {
+        self.serialize_f64(v as f64)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Amf0Value, Amf0Error> {
+        Ok(Amf0Value::Number(Amf0Number::new(v)))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Amf0Value, Amf0Error> {
+        string(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Amf0Value, Amf0Error> {
+        string(v.to_string())
+    }
+
+    /// A strict array of numbers, AMF0 has no binary type
+    fn serialize_bytes(self, v: &[u8]) -> Result<Amf0Value, Amf0Error> {
+        let values = v
+            .iter()
+            .map(|byte| Amf0Value::Number(Amf0Number::new(*byte as f64)))
+            .collect();
+
+        Ok(Amf0Value::StrictArray(Amf0StrictArray::new(values)))
+    }
+
+    fn serialize_none(self) -> Result<Amf0Value, Amf0Error> {
+        Ok(Amf0Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Amf0Value, Amf0Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Amf0Value, Amf0Error> {
+        Ok(Amf0Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Amf0Value, Amf0Error> {
+        Ok(Amf0Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<Amf0Value, Amf0Error> {
+        string(variant.to_string())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Amf0Value, Amf0Error> {
+        match value.serialize(self)? {
+            Amf0Value::Number(millis) if name == AMF0_DATE_NEWTYPE => {
+                Ok(Amf0Value::Date(Amf0Date::new(millis.value())))
+            }
+            value => Ok(value),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant_name: &'static str,
+        value: &T,
+    ) -> Result<Amf0Value, Amf0Error> {
+        variant(variant_name, value.serialize(self)?)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeArray, Amf0Error> {
+        Ok(SerializeArray {
+            variant: None,
+            values: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeArray, Amf0Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, Amf0Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeArray, Amf0Error> {
+        Ok(SerializeArray {
+            variant: Some(variant),
+            values: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap, Amf0Error> {
+        Ok(SerializeMap {
+            props: Vec::with_capacity(len.unwrap_or(0)),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeObject, Amf0Error> {
+        Ok(SerializeObject {
+            variant: None,
+            props: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeObject, Amf0Error> {
+        Ok(SerializeObject {
+            variant: Some(variant),
+            props: Vec::with_capacity(len),
+        })
+    }
+}
+
+/// Sequences, tuples and tuple variants
+struct SerializeArray {
+    variant: Option<&'static str>,
+    values: Vec<Amf0Value>,
+}
+
+impl SerializeArray {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Amf0Error> {
+        self.values.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Amf0Value, Amf0Error> {
+        let array = Amf0Value::StrictArray(Amf0StrictArray::new(self.values));
+
+        match self.variant {
+            Some(name) => variant(name, array),
+            None => Ok(array),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeArray {
+    type Ok = Amf0Value;
+    type Error = Amf0Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Amf0Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Amf0Value, Amf0Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for SerializeArray {
+    type Ok = Amf0Value;
+    type Error = Amf0Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Amf0Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Amf0Value, Amf0Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeArray {
+    type Ok = Amf0Value;
+    type Error = Amf0Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Amf0Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Amf0Value, Amf0Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeArray {
+    type Ok = Amf0Value;
+    type Error = Amf0Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Amf0Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Amf0Value, Amf0Error> {
+        self.finish()
+    }
+}
+
+/// Structs and struct variants
+struct SerializeObject {
+    variant: Option<&'static str>,
+    props: Vec<Amf0DataObjectProp>,
+}
+
+impl ser::SerializeStruct for SerializeObject {
+    type Ok = Amf0Value;
+    type Error = Amf0Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Amf0Error> {
+        self.props
+            .push(prop(key.to_string(), value.serialize(ValueSerializer)?)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Amf0Value, Amf0Error> {
+        let object = Amf0Value::Object(Amf0Object::new(self.props));
+
+        match self.variant {
+            Some(name) => variant(name, object),
+            None => Ok(object),
+        }
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeObject {
+    type Ok = Amf0Value;
+    type Error = Amf0Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Amf0Error> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<Amf0Value, Amf0Error> {
+        ser::SerializeStruct::end(self)
+    }
+}
+
+/// Maps, the keys must serialize as strings or numbers
+struct SerializeMap {
+    props: Vec<Amf0DataObjectProp>,
+    key: Option<String>,
+}
+
+impl ser::SerializeMap for SerializeMap {
+    type Ok = Amf0Value;
+    type Error = Amf0Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Amf0Error> {
+        let key = match key.serialize(ValueSerializer)? {
+            Amf0Value::String(key) => key.content,
+            Amf0Value::LongString(key) => key.content,
+            Amf0Value::Number(key) => key.value().to_string(),
+            _ => {
+                return Err(Amf0Error::Custom(
+                    "map key must be a string or a number".to_string(),
+                ));
+            }
+        };
+
+        self.key = Some(key);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Amf0Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Amf0Error::Custom("map value without a key".to_string()))?;
+
+        self.props
+            .push(prop(key, value.serialize(ValueSerializer)?)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Amf0Value, Amf0Error> {
+        Ok(Amf0Value::EcmaArray(Amf0EcmaArray::new(self.props)))
+    }
+}
+
+/// Deserializes a decoded `Amf0Value`
+pub struct ValueDeserializer(Amf0Value);
+
+impl<'de> IntoDeserializer<'de, Amf0Error> for Amf0Value {
+    type Deserializer = ValueDeserializer;
+
+    fn into_deserializer(self) -> ValueDeserializer {
+        ValueDeserializer(self)
+    }
+}
+
+/// Props of an object, ECMA array or typed object
+fn props(value: Amf0Value) -> Result<Vec<Amf0DataObjectProp>, Amf0Value> {
+    match value {
+        Amf0Value::Object(object) => Ok(object.props),
+        Amf0Value::EcmaArray(array) => Ok(array.props),
+        Amf0Value::TypedObject(object) => Ok(object.props),
+        value => Err(value),
+    }
+}
+
+fn unexpected(value: &Amf0Value) -> Unexpected<'_> {
+    match value {
+        Amf0Value::Reference(_) => Unexpected::Other("AMF0 reference"),
+        Amf0Value::Amf3(_) => Unexpected::Other("AMF3 value"),
+        _ => Unexpected::Other("AMF0 value"),
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident)*) => {$(
+        /// Numbers are doubles, an integer is a number without a fractional part
+        fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Amf0Error> {
+            match &self.0 {
+                Amf0Value::Number(n) if n.value().fract() == 0.0 && n.value() >= 0.0 => {
+                    visitor.visit_u64(n.value() as u64)
+                }
+                Amf0Value::Number(n) if n.value().fract() == 0.0 => {
+                    visitor.visit_i64(n.value() as i64)
+                }
+                _ => self.deserialize_any(visitor),
+            }
+        }
+    )*};
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Amf0Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Amf0Error> {
+        let value = match props(self.0) {
+            Ok(props) => {
+                let mut map =
+                    MapDeserializer::new(props.into_iter().map(|prop| (prop.name.key, prop.value)));
+
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+
+                return Ok(value);
+            }
+            Err(value) => value,
+        };
+
+        match value {
+            Amf0Value::Number(n) => visitor.visit_f64(n.value()),
+            Amf0Value::Bool(b) => visitor.visit_bool(b.value()),
+            Amf0Value::String(s) => visitor.visit_string(s.content),
+            Amf0Value::LongString(s) => visitor.visit_string(s.content),
+            Amf0Value::XmlDocument(s) => visitor.visit_string(s.content),
+            Amf0Value::Date(date) => visitor.visit_f64(date.millis),
+            Amf0Value::Null | Amf0Value::Undefined | Amf0Value::Unsupported => visitor.visit_unit(),
+            Amf0Value::StrictArray(array) => {
+                let mut seq = SeqDeserializer::new(array.values.into_iter());
+
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+
+                Ok(value)
+            }
+            value => Err(de::Error::invalid_type(unexpected(&value), &visitor)),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Amf0Error> {
+        match self.0 {
+            Amf0Value::Null | Amf0Value::Undefined => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Amf0Error> {
+        match self.0 {
+            Amf0Value::Date(date) if name == AMF0_DATE_NEWTYPE => {
+                visitor.visit_newtype_struct(date.millis.into_deserializer())
+            }
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Amf0Error> {
+        match props(self.0) {
+            Ok(props) if props.len() == 1 => {
+                let prop = props.into_iter().next().expect("one prop");
+
+                visitor.visit_enum(EnumDeserializer {
+                    variant: prop.name.key,
+                    value: Some(prop.value),
+                })
+            }
+            Err(Amf0Value::String(variant)) => visitor.visit_enum(EnumDeserializer {
+                variant: variant.content,
+                value: None,
+            }),
+            _ => Err(de::Error::invalid_type(
+                Unexpected::Other("AMF0 value"),
+                &"a string or an object with one key",
+            )),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool f32 f64 char str string bytes byte_buf unit unit_struct seq tuple tuple_struct map
+        struct identifier ignored_any
+    }
+}
+
+/// A unit variant is its name, other variants an object with the variant as its only key
+struct EnumDeserializer {
+    variant: String,
+    value: Option<Amf0Value>,
+}
+
+impl<'de> EnumAccess<'de> for EnumDeserializer {
+    type Error = Amf0Error;
+    type Variant = VariantDeserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantDeserializer), Amf0Error> {
+        let variant = seed.deserialize(IntoDeserializer::<Amf0Error>::into_deserializer(
+            self.variant,
+        ))?;
+
+        Ok((variant, VariantDeserializer(self.value)))
+    }
+}
+
+struct VariantDeserializer(Option<Amf0Value>);
+
+impl VariantDeserializer {
+    fn value(self) -> Result<ValueDeserializer, Amf0Error> {
+        self.0
+            .map(ValueDeserializer)
+            .ok_or_else(|| de::Error::invalid_type(Unexpected::UnitVariant, &"a variant with data"))
+    }
+}
+
+impl<'de> VariantAccess<'de> for VariantDeserializer {
+    type Error = Amf0Error;
+
+    fn unit_variant(self) -> Result<(), Amf0Error> {
+        match self.0 {
+            None | Some(Amf0Value::Null) => Ok(()),
+            Some(value) => Err(de::Error::invalid_type(
+                unexpected(&value),
+                &"a unit variant",
+            )),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, Amf0Error> {
+        seed.deserialize(self.value()?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Amf0Error> {
+        self.value()?.deserialize_any(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Amf0Error> {
+        self.value()?.deserialize_any(visitor)
+    }
+}
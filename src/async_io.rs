@@ -0,0 +1,367 @@
+use std::{
+    collections::VecDeque,
+    io::ErrorKind,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use byteorder::{BigEndian, ByteOrder};
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+
+use crate::{
+    demux::{FlvDemuxer, FlvEvent},
+    error::{ErrorContext, FlvError},
+    v1::{
+        header::{FLV_HEADER_DATA_OFFSET, FlvHeader},
+        limits::DecodeLimits,
+        tag::{FLV_TAG_HEADER_SIZE, FlvTag, encode_tag},
+    },
+};
+
+/// Size of the chunks read by `FlvStream`
+const FLV_STREAM_CHUNK_SIZE: usize = 8 * 1024;
+
+/// `FlvSink` writes its buffer out before accepting more tags once it reaches this size
+const FLV_SINK_BUFFER_SIZE: usize = 64 * 1024;
+
+// The async versions read the bytes of the whole header/tag without blocking and then decode them
+// with the sync decoders, so both share the same parsing code
+
+impl FlvHeader {
+    pub async fn decode_async<T: AsyncRead + Unpin>(stream: &mut T) -> Result<Self, FlvError> {
+        Self::decode_async_with_limits(stream, &DecodeLimits::default()).await
+    }
+    /// Decodes a header from untrusted input, extra bytes over `limits.max_total_alloc` are an
+    /// error before they are read
+    pub async fn decode_async_with_limits<T: AsyncRead + Unpin>(
+        stream: &mut T,
+        limits: &DecodeLimits,
+    ) -> Result<Self, FlvError> {
+        let mut buf = Vec::with_capacity(FLV_HEADER_DATA_OFFSET as usize);
+
+        read_counted(stream, FLV_HEADER_DATA_OFFSET as usize, &mut buf).await?;
+
+        // the extra bytes up to data_offset, read as they arrive instead of trusting data_offset
+        let extra_size = Self::decode_fixed(&mut &buf[..])?.extra_size();
+
+        limits
+            .check_header_extra(extra_size)
+            .in_field("header extra")?;
+
+        read_counted(stream, extra_size, &mut buf)
+            .await
+            .in_field("header extra")?;
+
+        Self::decode(&mut &buf[..])
+    }
+
+    pub async fn encode_async<T: AsyncWrite + Unpin>(
+        &self,
+        stream: &mut T,
+    ) -> Result<(), FlvError> {
+        let mut buf = Vec::with_capacity(self.data_offset as usize);
+
+        self.encode(&mut buf)?;
+        stream.write_all(&buf).await?;
+
+        Ok(())
+    }
+}
+
+impl FlvTag {
+    pub async fn decode_async<T: AsyncRead + Unpin>(stream: &mut T) -> Result<Self, FlvError> {
+        Self::decode_async_with_limits(stream, &DecodeLimits::default()).await
+    }
+    /// Decodes a tag from untrusted input, a tag over `limits` is an error before its data is
+    /// read
+    pub async fn decode_async_with_limits<T: AsyncRead + Unpin>(
+        stream: &mut T,
+        limits: &DecodeLimits,
+    ) -> Result<Self, FlvError> {
+        let mut buf = Vec::with_capacity(FLV_TAG_HEADER_SIZE);
+
+        read_counted(stream, FLV_TAG_HEADER_SIZE, &mut buf)
+            .await
+            .in_field("tag header")?;
+
+        let data_size = BigEndian::read_u24(&buf[1..4]);
+        limits.check_tag_size(data_size).in_field("tag header")?;
+
+        // data + PreviousTagSize
+        let size = data_size as usize + 4;
+        buf.reserve_exact(size);
+
+        read_counted(stream, size, &mut buf)
+            .await
+            .in_field("tag data")?;
+
+        Self::decode_with_limits(&mut &buf[..], limits)
+    }
+
+    pub async fn encode_async<T: AsyncWrite + Unpin>(
+        &self,
+        stream: &mut T,
+    ) -> Result<(), FlvError> {
+        let mut buf = Vec::new();
+
+        self.encode(&mut buf)?;
+        stream.write_all(&buf).await?;
+
+        Ok(())
+    }
+}
+
+/// Appends exactly `size` bytes of `stream` to `buf`, a stream that ends before is `Truncated`
+async fn read_counted<T: AsyncRead + Unpin>(
+    stream: &mut T,
+    size: usize,
+    buf: &mut Vec<u8>,
+) -> Result<(), FlvError> {
+    let got = stream.take(size as u64).read_to_end(buf).await?;
+
+    if got < size {
+        return Err(FlvError::Truncated {
+            expected: size,
+            got,
+        });
+    }
+
+    Ok(())
+}
+
+/// FlvStream: Async decoder that yields the tags of an `AsyncRead` as a `Stream`
+///
+/// The header is available through `header()` once the first bytes are read, the stream ends
+/// with `None` only if the reader ends right after a `previous_tag_size`
+pub struct FlvStream<R: AsyncRead + Unpin> {
+    stream: R,
+    demuxer: FlvDemuxer,
+    header: Option<FlvHeader>,
+    tags: VecDeque<FlvTag>,
+
+    /// Error found after the tags in `tags`, returned once they are yielded
+    error: Option<FlvError>,
+
+    chunk: Box<[u8]>,
+    finished: bool,
+}
+
+impl<R: AsyncRead + Unpin> FlvStream<R> {
+    pub fn new(stream: R) -> Self {
+        Self {
+            stream,
+            demuxer: FlvDemuxer::new(),
+            header: None,
+            tags: VecDeque::new(),
+            error: None,
+            chunk: vec![0_u8; FLV_STREAM_CHUNK_SIZE].into_boxed_slice(),
+            finished: false,
+        }
+    }
+
+    /// Bounds every tag by `limits`, see `FlvDemuxer::with_limits`
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.demuxer = self.demuxer.with_limits(limits);
+        self
+    }
+
+    pub fn header(&self) -> Option<&FlvHeader> {
+        self.header.as_ref()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.stream
+    }
+
+    pub fn into_inner(self) -> R {
+        self.stream
+    }
+
+    fn queue(&mut self, events: Vec<FlvEvent>) {
+        for event in events {
+            match event {
+                FlvEvent::Header(header) => self.header = Some(header),
+                FlvEvent::Tag(tag) => self.tags.push_back(tag),
+                FlvEvent::Skipped(_) => {}
+            }
+        }
+    }
+
+    /// Takes the last events at the end of the input, the demuxer holds back an error found after
+    /// the events of a chunk until the next call
+    fn finish(&mut self) {
+        loop {
+            match self.demuxer.finish() {
+                Ok(events) if events.is_empty() => break,
+                Ok(events) => self.queue(events),
+                Err(e) => {
+                    self.error = Some(e);
+                    return;
+                }
+            }
+        }
+
+        // an empty input is not a stream either, it ends before the header
+        if !self.demuxer.is_at_tag_boundary() {
+            self.error = Some(self.demuxer.truncated());
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for FlvStream<R> {
+    type Item = Result<FlvTag, FlvError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            if let Some(tag) = this.tags.pop_front() {
+                return Poll::Ready(Some(Ok(tag)));
+            }
+
+            if let Some(e) = this.error.take() {
+                return Poll::Ready(Some(Err(e)));
+            }
+
+            if this.finished {
+                return Poll::Ready(None);
+            }
+
+            let mut buf = ReadBuf::new(&mut this.chunk);
+
+            if let Err(e) = ready!(Pin::new(&mut this.stream).poll_read(cx, &mut buf)) {
+                this.finished = true;
+                return Poll::Ready(Some(Err(e.into())));
+            }
+
+            if buf.filled().is_empty() {
+                this.finished = true;
+                this.finish();
+                continue;
+            }
+
+            match this.demuxer.push(buf.filled()) {
+                Ok(events) => this.queue(events),
+                Err(e) => {
+                    this.finished = true;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
+    }
+}
+
+/// FlvSink: Async encoder that writes the header and then the tags sent to it as a `Sink`, the
+/// data size, the timestamp split and the previous tag size of every tag are computed
+pub struct FlvSink<W: AsyncWrite + Unpin> {
+    stream: W,
+
+    /// Encoded bytes not written yet
+    buffer: Vec<u8>,
+
+    /// Bytes of `buffer` already written
+    written: usize,
+
+    /// Reused to measure the data of each tag
+    scratch: Vec<u8>,
+}
+
+impl<W: AsyncWrite + Unpin> FlvSink<W> {
+    /// The header and the `PreviousTagSize0` are written with the first flush
+    pub fn new(stream: W, header: &FlvHeader) -> Result<Self, FlvError> {
+        let mut buffer = Vec::new();
+
+        header.encode(&mut buffer)?;
+        buffer.extend_from_slice(&[0_u8; 4]);
+
+        Ok(Self {
+            stream,
+            buffer,
+            written: 0,
+            scratch: Vec::new(),
+        })
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.stream
+    }
+
+    pub fn into_inner(self) -> W {
+        self.stream
+    }
+
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), FlvError>> {
+        while self.written < self.buffer.len() {
+            let n =
+                ready!(Pin::new(&mut self.stream).poll_write(cx, &self.buffer[self.written..]))?;
+
+            if n == 0 {
+                return Poll::Ready(Err(std::io::Error::from(ErrorKind::WriteZero).into()));
+            }
+
+            self.written += n;
+        }
+
+        self.buffer.clear();
+        self.written = 0;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> Sink<FlvTag> for FlvSink<W> {
+    type Error = FlvError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        if this.buffer.len() >= FLV_SINK_BUFFER_SIZE {
+            return this.poll_write_buffer(cx);
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, tag: FlvTag) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+
+        encode_tag(
+            &mut this.buffer,
+            &mut this.scratch,
+            &tag.data,
+            tag.encryption.as_ref(),
+            tag.timestamp,
+            tag.stream_id,
+        )?;
+
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_write_buffer(cx))?;
+        ready!(Pin::new(&mut this.stream).poll_flush(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+
+        ready!(this.poll_write_buffer(cx))?;
+        ready!(Pin::new(&mut this.stream).poll_shutdown(cx))?;
+
+        Poll::Ready(Ok(()))
+    }
+}
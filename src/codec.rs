@@ -0,0 +1,198 @@
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{
+    demux::{
+        decode_header_frame, header_frame_len, tag_frame_len, truncated_header_frame,
+        truncated_tag_frame,
+    },
+    error::FlvError,
+    v1::{
+        header::FlvHeader,
+        limits::DecodeLimits,
+        tag::{FlvTag, encode_tag, tag_type_of},
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header,
+    Tags,
+}
+
+/// FlvCodec: tokio-util codec for FLV streams
+///
+/// As a `Decoder` it consumes the header first (available through `header()`) and then yields one
+/// `FlvTag` per frame, as an `Encoder` it writes the header given to `with_header` before the
+/// first tag and computes the data size, timestamp split and previous tag size of every tag
+///
+/// ```ignore
+/// let mut tags = FramedRead::new(socket, FlvCodec::new());
+/// ```
+#[derive(Debug)]
+pub struct FlvCodec {
+    state: State,
+
+    /// Header decoded from the stream
+    header: Option<FlvHeader>,
+
+    limits: DecodeLimits,
+
+    /// Index of the next decoded tag
+    index: u64,
+
+    /// Offset of the next decoded frame from the start of the stream
+    offset: u64,
+
+    /// Header to encode before the first tag
+    encode_header: Option<FlvHeader>,
+
+    /// Reused to measure the data of each encoded tag
+    scratch: Vec<u8>,
+}
+
+impl Default for FlvCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlvCodec {
+    pub fn new() -> Self {
+        Self {
+            state: State::Header,
+            header: None,
+            limits: DecodeLimits::default(),
+            index: 0,
+            offset: 0,
+            encode_header: None,
+            scratch: Vec::new(),
+        }
+    }
+
+    /// The encoder writes `header` and `PreviousTagSize0` before the first tag
+    pub fn with_header(header: FlvHeader) -> Self {
+        Self {
+            encode_header: Some(header),
+            ..Self::new()
+        }
+    }
+
+    /// Bounds every decoded tag by `limits`, a tag over them is an error before its data is
+    /// buffered
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Header decoded from the stream, `None` until it is received
+    pub fn header(&self) -> Option<&FlvHeader> {
+        self.header.as_ref()
+    }
+
+    /// The pending header, if any, and the tag
+    fn encode_frame(&mut self, tag: &FlvTag, dst: &mut BytesMut) -> Result<(), FlvError> {
+        let mut stream = dst.writer();
+
+        if let Some(header) = &self.encode_header {
+            header.encode(&mut stream)?;
+            stream.get_mut().put_u32(0);
+        }
+
+        encode_tag(
+            &mut stream,
+            &mut self.scratch,
+            &tag.data,
+            tag.encryption.as_ref(),
+            tag.timestamp,
+            tag.stream_id,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Decoder for FlvCodec {
+    type Item = FlvTag;
+    type Error = FlvError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if self.state == State::Header {
+            let Some(size) = header_frame_len(src, &self.limits)? else {
+                return Ok(None);
+            };
+
+            self.header = Some(decode_header_frame(&src[..size], &self.limits)?);
+            self.state = State::Tags;
+
+            src.advance(size);
+            self.offset += size as u64;
+        }
+
+        let (index, offset) = (self.index, self.offset);
+        let context = |e: FlvError, tag_type| e.in_tag(index, offset, tag_type);
+
+        let size =
+            tag_frame_len(src, &self.limits).map_err(|e| context(e, Some(tag_type_of(src[0]))))?;
+
+        let Some(size) = size else {
+            return Ok(None);
+        };
+
+        let frame = src.split_to(size);
+        let tag_type = Some(tag_type_of(frame[0]));
+
+        // with `bytes` the payloads keep a reference to the frame instead of being copied
+        #[cfg(feature = "bytes")]
+        let tag = FlvTag::decode_bytes_with_limits(&frame.freeze(), &self.limits);
+
+        #[cfg(not(feature = "bytes"))]
+        let tag = crate::demux::decode_tag_frame(&frame, &self.limits);
+
+        let tag = tag.map_err(|e| context(e, tag_type))?;
+
+        self.index += 1;
+        self.offset += size as u64;
+
+        Ok(Some(tag))
+    }
+
+    /// Bytes left at the end of the stream are a partial frame, reported as `Truncated`
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(tag) = self.decode(src)? {
+            return Ok(Some(tag));
+        }
+
+        if src.is_empty() {
+            return Ok(None);
+        }
+
+        Err(match self.state {
+            State::Header => truncated_header_frame(src),
+            State::Tags => {
+                truncated_tag_frame(src).in_tag(self.index, self.offset, Some(tag_type_of(src[0])))
+            }
+        })
+    }
+}
+
+impl Encoder<FlvTag> for FlvCodec {
+    type Error = FlvError;
+
+    /// A tag that fails to encode leaves `dst` as it was, the header is still written before the
+    /// next tag
+    fn encode(&mut self, tag: FlvTag, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let start = dst.len();
+
+        match self.encode_frame(&tag, dst) {
+            Ok(()) => {
+                self.encode_header = None;
+                Ok(())
+            }
+            Err(e) => {
+                dst.truncate(start);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use aes::{
+    Aes128,
+    cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit, block_padding::Pkcs7},
+};
+
+#[cfg(feature = "file")]
+use crate::file::FlvFile;
+use crate::{
+    error::{ErrorContext, FlvError},
+    v1::{
+        Payload,
+        encryption::{EncryptionTagHeader, FLV_FILTER_IV_SIZE, FilterParams},
+        payload,
+        script::FlvScriptTag,
+        tag::{FlvTag, FlvTagData},
+    },
+};
+
+/// AES-128 key of the "Encryption" and "SE" filters
+pub type EncryptionKey = [u8; 16];
+
+/// IV of the body of a filtered tag
+pub type EncryptionIv = [u8; FLV_FILTER_IV_SIZE];
+
+type Aes128CbcEnc = cbc::Encryptor<Aes128>;
+type Aes128CbcDec = cbc::Decryptor<Aes128>;
+
+/// Decrypts the body of a filtered tag (AES-128-CBC, PKCS#7 padding), returns the clear tag with
+/// the Filter bit unset, a tag that is not filtered is returned as it is
+pub fn decrypt_tag(mut tag: FlvTag, key: &EncryptionKey) -> Result<FlvTag, FlvError> {
+    let Some(encryption) = tag.encryption.take() else {
+        return Ok(tag);
+    };
+
+    let iv = match encryption.filter {
+        FilterParams::Encryption { iv } | FilterParams::SelectiveEncryption { iv: Some(iv) } => {
+            Some(iv)
+        }
+        // selective encryption left this tag clear
+        FilterParams::SelectiveEncryption { iv: None } => None,
+        FilterParams::Other { name, .. } => return Err(FlvError::UnsupportedFilter(name)),
+    };
+
+    if let Some(iv) = iv
+        && let Some(body) = tag.data.payload_mut()
+    {
+        *body = decrypt(body, key, &iv)?;
+    }
+
+    // the clear script data can be decoded now
+    if let FlvTagData::EncryptedScript(data) = &tag.data {
+        let script = FlvScriptTag::decode(&mut &data[..], data.len()).in_field("script data")?;
+
+        tag.data = FlvTagData::Script(script);
+    }
+
+    Ok(tag)
+}
+
+/// Encrypts the body of an audio/video tag with the "Encryption" filter, returns the filtered tag
+///
+/// Script data is returned clear, players read `onMetaData` before they have the key, and so are
+/// tags of an unknown type, only audio, video and script data can be filtered
+pub fn encrypt_tag(
+    mut tag: FlvTag,
+    key: &EncryptionKey,
+    iv: EncryptionIv,
+) -> Result<FlvTag, FlvError> {
+    if tag.is_filtered() {
+        return Err(FlvError::AlreadyFiltered);
+    }
+
+    if !is_encryptable(&tag.data) {
+        return Ok(tag);
+    }
+
+    let Some(body) = tag.data.payload_mut() else {
+        return Ok(tag);
+    };
+
+    *body = encrypt(body, key, &iv);
+    tag.encryption = Some(EncryptionTagHeader::new(FilterParams::Encryption { iv }));
+
+    Ok(tag)
+}
+
+/// Encrypts every audio/video tag of `file`, `iv` is called once per encrypted tag to get its IV
+#[cfg(feature = "file")]
+pub fn encrypt_file(
+    file: FlvFile,
+    key: &EncryptionKey,
+    mut iv: impl FnMut(&FlvTag) -> EncryptionIv,
+) -> Result<FlvFile, FlvError> {
+    let tags = file
+        .tags
+        .into_iter()
+        .map(|tag| {
+            if !is_encryptable(&tag.data) {
+                return Ok(tag);
+            }
+
+            let iv = iv(&tag);
+            encrypt_tag(tag, key, iv)
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(FlvFile {
+        header: file.header,
+        tags,
+    })
+}
+
+/// Decrypts every filtered tag of `file`
+#[cfg(feature = "file")]
+pub fn decrypt_file(file: FlvFile, key: &EncryptionKey) -> Result<FlvFile, FlvError> {
+    let tags = file
+        .tags
+        .into_iter()
+        .map(|tag| decrypt_tag(tag, key))
+        .collect::<Result<_, _>>()?;
+
+    Ok(FlvFile {
+        header: file.header,
+        tags,
+    })
+}
+
+/// Audio and video bodies are encrypted, script data and unknown tags are left clear
+fn is_encryptable(data: &FlvTagData) -> bool {
+    matches!(data, FlvTagData::Audio(_) | FlvTagData::Video(_))
+}
+
+fn encrypt(body: &[u8], key: &EncryptionKey, iv: &EncryptionIv) -> Payload {
+    payload(Aes128CbcEnc::new(key.into(), iv.into()).encrypt_padded_vec_mut::<Pkcs7>(body))
+}
+
+fn decrypt(body: &[u8], key: &EncryptionKey, iv: &EncryptionIv) -> Result<Payload, FlvError> {
+    let clear = Aes128CbcDec::new(key.into(), iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(body)
+        .map_err(|_| FlvError::DecryptionFailed)?;
+
+    Ok(payload(clear))
+}
@@ -0,0 +1,397 @@
+use std::ops::Range;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::{
+    error::{ErrorContext, FlvError},
+    v1::{
+        header::{FLV_HEADER_DATA_OFFSET, FlvHeader},
+        limits::DecodeLimits,
+        tag::{
+            FLV_TAG_HEADER_SIZE, FLV_TAG_MAX_DATA_SIZE, FLV_TAG_RESERVED_BITS, FlvTag, FlvTagType,
+            calc_previous_tag_size, tag_type_of,
+        },
+    },
+};
+
+/// Size of a PreviousTagSize field
+const FLV_PREVIOUS_TAG_SIZE_SIZE: usize = 4;
+
+#[derive(Debug)]
+pub enum FlvEvent {
+    Header(FlvHeader),
+    Tag(FlvTag),
+
+    /// Offsets (from the start of the stream) of bytes dropped in recovery mode
+    Skipped(Range<u64>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Header,
+    Tags,
+}
+
+/// FlvDemuxer: Sans-IO decoder, it receives the bytes of an FLV stream in chunks of any size and
+/// returns the header and the tags as soon as they are complete, partial tags are buffered until
+/// the rest of their bytes arrive
+///
+/// ```ignore
+/// let mut demuxer = FlvDemuxer::new();
+///
+/// while let Some(chunk) = socket.recv()? {
+///     for event in demuxer.push(&chunk)? {
+///         // ...
+///     }
+/// }
+/// ```
+///
+/// In recovery mode (`with_recovery`) corrupt data doesn't stop the demuxer: a tag is corrupt if
+/// its header is not plausible (reserved bits or stream id set), its `previous_tag_size` doesn't
+/// match its size or its data can't be decoded, the demuxer then scans forward for the next valid
+/// tag of a known type and reports the bytes it dropped with `FlvEvent::Skipped`
+#[derive(Debug)]
+pub struct FlvDemuxer {
+    buffer: Vec<u8>,
+
+    /// Start of the bytes not consumed yet
+    start: usize,
+
+    /// Bytes dropped from the front of `buffer`, `consumed + start` is the stream offset
+    consumed: u64,
+
+    state: State,
+
+    /// Index of the next tag
+    index: u64,
+
+    recovery: bool,
+
+    limits: DecodeLimits,
+
+    /// Stream offset where the bytes being skipped start
+    skipped_from: Option<u64>,
+
+    /// No more bytes will be pushed
+    finished: bool,
+}
+
+impl Default for FlvDemuxer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FlvDemuxer {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            start: 0,
+            consumed: 0,
+            state: State::Header,
+            index: 0,
+            recovery: false,
+            limits: DecodeLimits::default(),
+            skipped_from: None,
+            finished: false,
+        }
+    }
+
+    /// Demuxer that resynchronises after corrupt tags instead of failing
+    pub fn with_recovery() -> Self {
+        Self {
+            recovery: true,
+            ..Self::new()
+        }
+    }
+
+    /// Bounds every tag by `limits`, a tag over them is an error (skipped in recovery mode), its
+    /// data is never buffered
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Buffers `data` and returns every event completed by it
+    ///
+    /// On a decode error the invalid bytes stay buffered: the events before it are returned first
+    /// and the error is returned by the next call
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<FlvEvent>, FlvError> {
+        // drop the consumed bytes, at most a partial tag is moved
+        self.buffer.drain(..self.start);
+        self.consumed += self.start as u64;
+        self.start = 0;
+
+        self.buffer.extend_from_slice(data);
+
+        let mut events = Vec::new();
+
+        loop {
+            match self.next_event() {
+                Ok(Some(event)) => events.push(event),
+                Ok(None) => break,
+                Err(e) if events.is_empty() => return Err(e),
+                Err(_) => break,
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Marks the end of the stream and returns the last events
+    ///
+    /// A partial tag left in the buffer is an error, in recovery mode it is reported as skipped
+    pub fn finish(&mut self) -> Result<Vec<FlvEvent>, FlvError> {
+        self.finished = true;
+
+        let events = self.push(&[])?;
+
+        if events.is_empty() && self.buffered() > 0 {
+            return Err(self.truncated());
+        }
+
+        Ok(events)
+    }
+
+    /// Offset from the start of the stream of the next byte to parse
+    pub fn offset(&self) -> u64 {
+        self.consumed + self.start as u64
+    }
+
+    /// Bytes received that are not part of a returned event yet
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.start
+    }
+
+    /// Whether the stream can end here without losing data: the header was received and there is
+    /// no partial tag buffered
+    pub fn is_at_tag_boundary(&self) -> bool {
+        self.state == State::Tags && self.buffered() == 0
+    }
+
+    /// Error for a stream that ends in the middle of the frame being buffered
+    pub(crate) fn truncated(&self) -> FlvError {
+        let available = &self.buffer[self.start..];
+
+        match self.state {
+            State::Header => truncated_header_frame(available),
+            State::Tags => {
+                let tag_type = available.first().map(|&t| tag_type_of(t));
+
+                truncated_tag_frame(available).in_tag(self.index, self.offset(), tag_type)
+            }
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Option<FlvEvent>, FlvError> {
+        let available = &self.buffer[self.start..];
+
+        match self.state {
+            State::Header => {
+                let Some(size) = header_frame_len(available, &self.limits)? else {
+                    return Ok(None);
+                };
+
+                let header = decode_header_frame(&available[..size], &self.limits)?;
+
+                self.start += size;
+                self.state = State::Tags;
+
+                Ok(Some(FlvEvent::Header(header)))
+            }
+            State::Tags if self.recovery => self.next_tag_lenient(),
+            State::Tags => {
+                let (index, offset) = (self.index, self.offset());
+                let context =
+                    |e: FlvError| e.in_tag(index, offset, Some(tag_type_of(available[0])));
+
+                let Some(size) = tag_frame_len(available, &self.limits).map_err(context)? else {
+                    return Ok(None);
+                };
+
+                let tag = decode_tag_frame(&available[..size], &self.limits).map_err(context)?;
+
+                self.start += size;
+                self.index += 1;
+
+                Ok(Some(FlvEvent::Tag(tag)))
+            }
+        }
+    }
+}
+
+impl FlvDemuxer {
+    fn next_tag_lenient(&mut self) -> Result<Option<FlvEvent>, FlvError> {
+        loop {
+            let available = &self.buffer[self.start..];
+            let offset = self.offset();
+
+            let resyncing = self.skipped_from.is_some();
+
+            let size = if available.len() < FLV_TAG_HEADER_SIZE {
+                None
+            } else if !is_plausible_tag_header(available, &self.limits, resyncing) {
+                self.skip(1);
+                continue;
+            } else {
+                tag_frame_len(available, &self.limits)?
+            };
+
+            let Some(size) = size else {
+                if !self.finished {
+                    return Ok(None);
+                }
+
+                // there won't be more bytes to complete the tag
+                if available.is_empty() {
+                    return Ok(self.take_skipped(offset));
+                }
+
+                self.skip(1);
+                continue;
+            };
+
+            let data_size = (size - FLV_TAG_HEADER_SIZE - FLV_PREVIOUS_TAG_SIZE_SIZE) as u32;
+            let previous_tag_size =
+                BigEndian::read_u32(&available[size - FLV_PREVIOUS_TAG_SIZE_SIZE..size]);
+
+            if previous_tag_size != calc_previous_tag_size(data_size) {
+                self.skip(1);
+                continue;
+            }
+
+            // the bytes skipped are reported before the tag found after them
+            if let Some(skipped) = self.take_skipped(offset) {
+                return Ok(Some(skipped));
+            }
+
+            let tag = decode_tag_frame(&self.buffer[self.start..self.start + size], &self.limits);
+
+            self.start += size;
+            self.index += 1;
+
+            // the tag is well framed, only its data is invalid, so the whole tag is skipped
+            return Ok(Some(match tag {
+                Ok(tag) => FlvEvent::Tag(tag),
+                Err(_) => FlvEvent::Skipped(offset..offset + size as u64),
+            }));
+        }
+    }
+
+    fn skip(&mut self, n: usize) {
+        self.skipped_from.get_or_insert(self.offset());
+        self.start += n;
+    }
+
+    fn take_skipped(&mut self, offset: u64) -> Option<FlvEvent> {
+        self.skipped_from
+            .take()
+            .map(|from| FlvEvent::Skipped(from..offset))
+    }
+}
+
+/// Checks the fields of a tag header that have a single valid value: reserved bits and stream id
+/// (always 0), and that the tag is within `limits`
+///
+/// While `resyncing` after corrupt data the tag type must also be known, unknown tag types are only
+/// passed through when the demuxer is in sync
+fn is_plausible_tag_header(buf: &[u8], limits: &DecodeLimits, resyncing: bool) -> bool {
+    let reserved = buf[0] & FLV_TAG_RESERVED_BITS == 0;
+    let tag_type = !resyncing || !matches!(tag_type_of(buf[0]), FlvTagType::Unknown(_));
+    let data_size = BigEndian::read_u24(&buf[1..4]);
+    let data_size = data_size <= FLV_TAG_MAX_DATA_SIZE && limits.check_tag_size(data_size).is_ok();
+    let stream_id = BigEndian::read_u24(&buf[8..11]) == 0;
+
+    reserved && tag_type && data_size && stream_id
+}
+
+/// Length of the header, its extra bytes up to `data_offset` and `PreviousTagSize0`, `None` if
+/// `buf` doesn't contain all of them yet
+///
+/// The 9 bytes of the header are checked as soon as they are available, and the extra bytes must
+/// be within `limits`
+pub(crate) fn header_frame_len(
+    buf: &[u8],
+    limits: &DecodeLimits,
+) -> Result<Option<usize>, FlvError> {
+    if buf.len() < FLV_HEADER_DATA_OFFSET as usize {
+        return Ok(None);
+    }
+
+    let header = FlvHeader::decode_fixed(&mut &buf[..])?;
+
+    limits
+        .check_header_extra(header.extra_size())
+        .in_field("header extra")?;
+
+    let size = header.data_offset as usize + FLV_PREVIOUS_TAG_SIZE_SIZE;
+
+    Ok((buf.len() >= size).then_some(size))
+}
+
+/// Length of the tag at the start of `buf` including its `previous_tag_size`, `None` if `buf`
+/// doesn't contain the whole tag yet
+///
+/// A tag over `limits` is an error as soon as its header is available, before its data is buffered
+pub(crate) fn tag_frame_len(buf: &[u8], limits: &DecodeLimits) -> Result<Option<usize>, FlvError> {
+    if buf.len() < FLV_TAG_HEADER_SIZE {
+        return Ok(None);
+    }
+
+    let data_size = BigEndian::read_u24(&buf[1..4]);
+
+    limits.check_tag_size(data_size).in_field("tag header")?;
+
+    let size = FLV_TAG_HEADER_SIZE + data_size as usize + FLV_PREVIOUS_TAG_SIZE_SIZE;
+
+    Ok((buf.len() >= size).then_some(size))
+}
+
+/// Error for a stream that ends after the first `buf.len()` bytes of the header frame
+pub(crate) fn truncated_header_frame(buf: &[u8]) -> FlvError {
+    let data_offset = match FlvHeader::decode_fixed(&mut &buf[..]) {
+        Ok(header) => header.data_offset as usize,
+        Err(_) => FLV_HEADER_DATA_OFFSET as usize,
+    };
+
+    FlvError::Truncated {
+        expected: data_offset + FLV_PREVIOUS_TAG_SIZE_SIZE,
+        got: buf.len(),
+    }
+}
+
+/// Error for a stream that ends after the first `buf.len()` bytes of a tag frame, the whole frame
+/// is expected once its data size is known
+pub(crate) fn truncated_tag_frame(buf: &[u8]) -> FlvError {
+    let expected = if buf.len() < FLV_TAG_HEADER_SIZE {
+        FLV_TAG_HEADER_SIZE
+    } else {
+        let data_size = BigEndian::read_u24(&buf[1..4]) as usize;
+
+        FLV_TAG_HEADER_SIZE + data_size + FLV_PREVIOUS_TAG_SIZE_SIZE
+    };
+
+    FlvError::Truncated {
+        expected,
+        got: buf.len(),
+    }
+}
+
+/// `frame` must be exactly `header_frame_len` bytes
+pub(crate) fn decode_header_frame(
+    frame: &[u8],
+    limits: &DecodeLimits,
+) -> Result<FlvHeader, FlvError> {
+    let header = FlvHeader::decode_with_limits(&mut &frame[..], limits)?;
+
+    if BigEndian::read_u32(&frame[header.data_offset as usize..]) != 0 {
+        return Err(FlvError::InvalidFile);
+    }
+
+    Ok(header)
+}
+
+/// `frame` must be exactly `tag_frame_len` bytes
+pub(crate) fn decode_tag_frame(frame: &[u8], limits: &DecodeLimits) -> Result<FlvTag, FlvError> {
+    FlvTag::decode_with_limits(&mut &frame[..], limits)
+}
@@ -2,7 +2,10 @@ use num_enum::TryFromPrimitiveError;
 use thiserror::Error;
 
 use crate::v1::{
-    audio::{SoundFormat, SoundRate, SoundSize, SoundType}, script::Amf0Error, tag::FlvTagType, video::{CodecId, FrameType}
+    amf3::Amf3Error,
+    audio::{SoundRate, SoundSize, SoundType},
+    script::Amf0Error,
+    tag::FlvTagType,
 };
 
 #[derive(Debug, Error)]
@@ -19,32 +22,144 @@ pub enum FlvError {
     #[error("Data offset")]
     InvalidDataOffset,
 
-
-
     #[error("Invalid File")]
     InvalidFile,
 
-    #[error("Amf0 Parser Error: {0}")]
-    Amf0Error(#[from] Amf0Error),
+    #[error("Tag too large: {0} bytes")]
+    TagTooLarge(usize),
+
+    /// The stream ended `expected - got` bytes before the end of a field
+    #[error("Truncated: expected {expected} bytes, got {got}")]
+    Truncated { expected: usize, got: usize },
+
+    /// The filter of a filtered tag is not one of the filters of the spec
+    #[error("Unsupported filter: {0}")]
+    UnsupportedFilter(String),
+
+    #[error("Tag is already filtered")]
+    AlreadyFiltered,
 
-    #[error("Invalid Codec Id: {0}")]
-    InvalidCodecId(#[from] TryFromPrimitiveError<CodecId>),
+    /// The body is not a multiple of the block size or its padding is invalid, usually a wrong key
+    #[error("Decryption failed")]
+    DecryptionFailed,
 
-    #[error("Invalid Frame Type: {0}")]
-    InvalidFrameType(#[from] TryFromPrimitiveError<FrameType>),
+    /// The `data_size` of a tag can't hold the headers of its data
+    #[error("Data size too small: {0} bytes")]
+    DataSizeTooSmall(usize),
 
-    #[error("Invalid Tag Type: {0}")]
-    InvalidTagType(#[from] TryFromPrimitiveError<FlvTagType>),
+    /// A `DecodeLimits` bound, named by the field, was exceeded
+    #[error("Decode limit exceeded: {0}")]
+    LimitExceeded(&'static str),
 
-    #[error("Invalid Sound Format: {0}")]
-    InvalidSoundFormat(#[from] TryFromPrimitiveError<SoundFormat>),
+    #[error("Amf0 Parser Error: {0}")]
+    Amf0Error(Amf0Error),
 
     #[error("Invalid Sound Rate: {0}")]
     InvalidSoundRate(#[from] TryFromPrimitiveError<SoundRate>),
 
     #[error("Invalid Sound Size: {0}")]
     InvalidSoundSize(#[from] TryFromPrimitiveError<SoundSize>),
-    
+
     #[error("Invalid Sound Type: {0}")]
     InvalidSoundType(#[from] TryFromPrimitiveError<SoundType>),
+
+    /// The field that was being decoded when `source` happened
+    #[error("{field}: {source}")]
+    InField {
+        field: &'static str,
+        source: Box<FlvError>,
+    },
+
+    /// Position of the tag that failed to decode, `offset` is counted from the start of the file
+    #[error("tag #{index} @ {offset:#x}, {source}")]
+    InTag {
+        index: u64,
+        offset: u64,
+        tag_type: Option<FlvTagType>,
+        source: Box<FlvError>,
+    },
+}
+
+impl From<Amf0Error> for FlvError {
+    fn from(e: Amf0Error) -> Self {
+        match e {
+            Amf0Error::Truncated { expected, got }
+            | Amf0Error::Amf3Error(Amf3Error::Truncated { expected, got }) => {
+                Self::Truncated { expected, got }
+            }
+            e => Self::Amf0Error(e),
+        }
+    }
+}
+
+impl FlvError {
+    pub(crate) fn in_field(self, field: &'static str) -> Self {
+        Self::InField {
+            field,
+            source: Box::new(self),
+        }
+    }
+
+    pub(crate) fn in_tag(self, index: u64, offset: u64, tag_type: Option<FlvTagType>) -> Self {
+        Self::InTag {
+            index,
+            offset,
+            tag_type,
+            source: Box::new(self),
+        }
+    }
+
+    /// The error without its context
+    pub fn root(&self) -> &FlvError {
+        match self {
+            Self::InField { source, .. } | Self::InTag { source, .. } => source.root(),
+            e => e,
+        }
+    }
+
+    /// Index of the tag that failed to decode, the first tag is 0
+    pub fn tag_index(&self) -> Option<u64> {
+        match self {
+            Self::InTag { index, .. } => Some(*index),
+            Self::InField { source, .. } => source.tag_index(),
+            _ => None,
+        }
+    }
+
+    /// Offset from the start of the file of the tag that failed to decode
+    pub fn offset(&self) -> Option<u64> {
+        match self {
+            Self::InTag { offset, .. } => Some(*offset),
+            Self::InField { source, .. } => source.offset(),
+            _ => None,
+        }
+    }
+
+    pub fn tag_type(&self) -> Option<FlvTagType> {
+        match self {
+            Self::InTag { tag_type, .. } => *tag_type,
+            Self::InField { source, .. } => source.tag_type(),
+            _ => None,
+        }
+    }
+
+    /// Innermost field that was being decoded
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            Self::InField { field, source } => source.field().or(Some(field)),
+            Self::InTag { source, .. } => source.field(),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) trait ErrorContext<T> {
+    /// Adds the field being decoded to the error
+    fn in_field(self, field: &'static str) -> Result<T, FlvError>;
+}
+
+impl<T, E: Into<FlvError>> ErrorContext<T> for Result<T, E> {
+    fn in_field(self, field: &'static str) -> Result<T, FlvError> {
+        self.map_err(|e| e.into().in_field(field))
+    }
 }
@@ -1,10 +1,19 @@
-use std::io::ErrorKind;
+use std::ops::Range;
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
 use crate::{
+    demux::FlvEvent,
     error::FlvError,
-    v1::{header::FlvHeader, tag::FlvTag},
+    reader::{FlvLenientReader, FlvReader},
+    v1::{
+        audio::FlvAudioTag,
+        header::{FlvHeader, HeaderFlags},
+        script::FlvScriptTag,
+        tag::{FlvTag, FlvTagData},
+        timestamp::{FlvTimestamp, TimestampUnwrapper},
+        video::FlvVideoData,
+    },
 };
 
 /// FlvFile: Encoder, Decoder, receives a header and a series of tags and inserts them into an FLV file, as well as receiving an FLV file and generating the respective tags
@@ -17,29 +26,35 @@ pub struct FlvFile {
 impl FlvFile {
     /// Decodes a FlvFile struct to a valid FLV file
     pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, FlvError> {
-        let header = FlvHeader::decode(stream)?;
+        let mut reader = FlvReader::new(stream)?;
 
-        let p = stream.read_u32::<BigEndian>()?;
+        let tags = reader.by_ref().collect::<Result<Vec<_>, _>>()?;
 
-        if p != 0 {
-            return Err(FlvError::InvalidFile);
-        }
+        let (header, _) = reader.into_parts();
 
-        let mut tags = Vec::new();
+        Ok(FlvFile { header, tags })
+    }
+    /// Decodes a damaged FLV file keeping every tag that can be recovered, also returns the byte
+    /// ranges skipped to get past the corrupt data
+    pub fn decode_lenient<T: ReadBytesExt>(
+        stream: &mut T,
+    ) -> Result<(Self, Vec<Range<u64>>), FlvError> {
+        let mut reader = FlvLenientReader::new(stream)?;
 
-        loop {
-            let tag = FlvTag::decode(stream);
+        let mut tags = Vec::new();
+        let mut skipped = Vec::new();
 
-            match tag {
-                Ok(t) => tags.push(t),
-                Err(e) => match e {
-                    FlvError::IoError(e) if e.kind() == ErrorKind::UnexpectedEof => break,
-                    e => return Err(e),
-                },
+        for event in reader.by_ref() {
+            match event? {
+                FlvEvent::Tag(tag) => tags.push(tag),
+                FlvEvent::Skipped(range) => skipped.push(range),
+                FlvEvent::Header(_) => {}
             }
         }
 
-        Ok(FlvFile { header, tags })
+        let (header, _) = reader.into_parts();
+
+        Ok((FlvFile { header, tags }, skipped))
     }
     /// Encodes a valid FLV file to a FlvFile struct
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), FlvError> {
@@ -51,3 +66,113 @@ impl FlvFile {
         Ok(())
     }
 }
+
+/// FlvFileBuilder: Collects media packets and script data and builds an `FlvFile` with the header
+/// flags and the order of its tags derived from them
+///
+/// The first `onMetaData`, the first AVC sequence header and the first AAC sequence header are
+/// moved before every other tag, in that order, and the rest of the tags are sorted by timestamp
+/// (tags with the same timestamp keep the order they were added in)
+///
+/// Timestamps are compared as unwrapped by `TimestampUnwrapper` in the order the tags were added,
+/// so a stream that rolls over the 32 bits keeps its order. The only timestamps rewritten are
+/// those of the tags moved to the start, which get the timestamp of the earliest tag
+#[derive(Debug, Default)]
+pub struct FlvFileBuilder {
+    tags: Vec<FlvTag>,
+}
+
+impl FlvFileBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_video(
+        &mut self,
+        video: FlvVideoData,
+        timestamp: impl Into<FlvTimestamp>,
+    ) -> &mut Self {
+        self.add_tag(FlvTag::new_video(video, timestamp))
+    }
+
+    pub fn add_audio(
+        &mut self,
+        audio: FlvAudioTag,
+        timestamp: impl Into<FlvTimestamp>,
+    ) -> &mut Self {
+        self.add_tag(FlvTag::new_audio(audio, timestamp))
+    }
+
+    pub fn add_script(
+        &mut self,
+        script: FlvScriptTag,
+        timestamp: impl Into<FlvTimestamp>,
+    ) -> &mut Self {
+        self.add_tag(FlvTag::new_script(script, timestamp))
+    }
+
+    pub fn add_tag(&mut self, tag: FlvTag) -> &mut Self {
+        self.tags.push(tag);
+        self
+    }
+
+    /// The header has the AUDIO/VIDEO flags of the tags added, the tags moved to the start get
+    /// the timestamp of the earliest tag, so timestamps never go back
+    pub fn build(self) -> FlvFile {
+        let mut flags = HeaderFlags::empty();
+
+        for tag in &self.tags {
+            match tag.data {
+                FlvTagData::Video(_) => flags |= HeaderFlags::VIDEO,
+                FlvTagData::Audio(_) => flags |= HeaderFlags::AUDIO,
+                _ => {}
+            }
+        }
+
+        let mut unwrapper = TimestampUnwrapper::new();
+        let mut tags: Vec<_> = self
+            .tags
+            .into_iter()
+            .map(|tag| (unwrapper.unwrap(tag.timestamp), tag))
+            .collect();
+
+        // stable, so tags with the same timestamp stay in order
+        tags.sort_by_key(|(time, _)| *time);
+
+        let start = tags
+            .first()
+            .map_or(FlvTimestamp::ZERO, |(_, tag)| tag.timestamp);
+
+        let mut leading: [Option<FlvTag>; 3] = [None, None, None];
+        let mut rest = Vec::with_capacity(tags.len());
+
+        for (_, tag) in tags {
+            let slot = match &tag.data {
+                FlvTagData::Script(script) if script.is_metadata() => 0,
+                FlvTagData::Video(video) if video.is_sequence_header() => 1,
+                FlvTagData::Audio(audio) if audio.is_sequence_header() => 2,
+                _ => {
+                    rest.push(tag);
+                    continue;
+                }
+            };
+
+            // later sequence headers are configuration changes, they stay where they are
+            if leading[slot].is_none() {
+                leading[slot] = Some(tag);
+            } else {
+                rest.push(tag);
+            }
+        }
+
+        let leading = leading.into_iter().flatten().map(|mut tag| {
+            tag.timestamp = start;
+            tag
+        });
+
+        FlvFile {
+            header: FlvHeader::new(flags),
+            tags: leading.chain(rest).collect(),
+        }
+    }
+}
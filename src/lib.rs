@@ -1,7 +1,31 @@
 #[cfg(feature = "v1")]
 pub mod v1;
 
+#[cfg(feature = "v1")]
+pub mod demux;
+
 #[cfg(feature = "file")]
 pub mod file;
 
+#[cfg(feature = "file")]
+pub mod reader;
+
+#[cfg(feature = "file")]
+pub mod writer;
+
+#[cfg(feature = "file")]
+pub mod scan;
+
+#[cfg(feature = "async")]
+pub mod async_io;
+
+#[cfg(feature = "codec")]
+pub mod codec;
+
+#[cfg(feature = "encryption")]
+pub mod crypto;
+
+#[cfg(feature = "serde")]
+pub mod amf0;
+
 pub mod error;
@@ -0,0 +1,224 @@
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read},
+};
+
+use crate::{
+    demux::{FlvDemuxer, FlvEvent},
+    error::{ErrorContext, FlvError},
+    v1::{
+        header::FlvHeader,
+        limits::DecodeLimits,
+        read_u32_be,
+        tag::{FlvTag, tag_type_of},
+    },
+};
+
+/// FlvReader: Decoder that parses the header once and then yields the tags one at a time, so
+/// files of any size (or live pipes) can be processed in constant memory
+///
+/// The iterator ends with `None` only when the stream ends cleanly right after a
+/// `previous_tag_size`, a stream that ends in the middle of a tag yields an error instead
+pub struct FlvReader<R: Read> {
+    stream: R,
+    header: FlvHeader,
+    finished: bool,
+    limits: DecodeLimits,
+
+    /// Index of the next tag
+    index: u64,
+
+    /// Offset of the next tag from the start of the file
+    offset: u64,
+}
+
+impl<R: Read> FlvReader<R> {
+    /// Decodes the FLV header and the `PreviousTagSize0` that follows it
+    pub fn new(stream: R) -> Result<Self, FlvError> {
+        Self::new_with_limits(stream, DecodeLimits::default())
+    }
+
+    /// Decodes untrusted input, the header and every tag are bounded by `limits`, the size of the
+    /// header is checked before its extra bytes are read
+    pub fn new_with_limits(mut stream: R, limits: DecodeLimits) -> Result<Self, FlvError> {
+        let header = FlvHeader::decode_with_limits(&mut stream, &limits)?;
+
+        let p = read_u32_be(&mut stream).in_field("previous tag size")?;
+
+        if p != 0 {
+            return Err(FlvError::InvalidFile);
+        }
+
+        let offset = header.data_offset as u64 + 4;
+
+        Ok(Self {
+            stream,
+            header,
+            finished: false,
+            limits,
+            index: 0,
+            offset,
+        })
+    }
+
+    pub fn header(&self) -> &FlvHeader {
+        &self.header
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.stream
+    }
+
+    pub fn into_parts(self) -> (FlvHeader, R) {
+        (self.header, self.stream)
+    }
+
+    /// Returns `Ok(None)` if the stream is at EOF before the first byte of the tag
+    fn next_tag(&mut self) -> Result<Option<FlvTag>, FlvError> {
+        let mut tag_type = [0_u8; 1];
+
+        loop {
+            match self.stream.read(&mut tag_type) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        // the tag type is already consumed, so it is put back in front of the stream
+        // `take` counts the bytes read, so the offset follows the input even if the tag would be
+        // encoded with a different size
+        let mut stream = (&tag_type[..]).chain(&mut self.stream).take(u64::MAX);
+
+        let tag = FlvTag::decode_with_limits(&mut stream, &self.limits)
+            .map_err(|e| e.in_tag(self.index, self.offset, Some(tag_type_of(tag_type[0]))))?;
+
+        self.index += 1;
+        self.offset += u64::MAX - stream.limit();
+
+        Ok(Some(tag))
+    }
+}
+
+impl<R: Read> Iterator for FlvReader<R> {
+    type Item = Result<FlvTag, FlvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let tag = self.next_tag().transpose();
+
+        if !matches!(tag, Some(Ok(_))) {
+            self.finished = true;
+        }
+
+        tag
+    }
+}
+
+/// Size of the chunks read by `FlvLenientReader`
+const FLV_READER_CHUNK_SIZE: usize = 8 * 1024;
+
+/// FlvLenientReader: Decoder for damaged files (e.g. from a crashed encoder), it yields the tags
+/// and the byte ranges skipped to resynchronise after corrupt data as `FlvEvent::Tag` and
+/// `FlvEvent::Skipped`, see `FlvDemuxer::with_recovery`
+pub struct FlvLenientReader<R: Read> {
+    events: LenientEvents<R>,
+    header: FlvHeader,
+}
+
+impl<R: Read> FlvLenientReader<R> {
+    /// Decodes the FLV header, it must be valid
+    pub fn new(stream: R) -> Result<Self, FlvError> {
+        let mut events = LenientEvents {
+            stream,
+            demuxer: FlvDemuxer::with_recovery(),
+            events: VecDeque::new(),
+            chunk: vec![0_u8; FLV_READER_CHUNK_SIZE].into_boxed_slice(),
+            finished: false,
+        };
+
+        let header = match events.next_event()? {
+            Some(FlvEvent::Header(header)) => header,
+            _ => return Err(std::io::Error::from(ErrorKind::UnexpectedEof).into()),
+        };
+
+        Ok(Self { events, header })
+    }
+
+    pub fn header(&self) -> &FlvHeader {
+        &self.header
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.events.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.events.stream
+    }
+
+    pub fn into_parts(self) -> (FlvHeader, R) {
+        (self.header, self.events.stream)
+    }
+}
+
+impl<R: Read> Iterator for FlvLenientReader<R> {
+    type Item = Result<FlvEvent, FlvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let event = self.events.next_event().transpose();
+
+        if matches!(event, Some(Err(_))) {
+            self.events.finished = true;
+            self.events.events.clear();
+        }
+
+        event
+    }
+}
+
+/// Reads `stream` in chunks through a recovering demuxer
+struct LenientEvents<R: Read> {
+    stream: R,
+    demuxer: FlvDemuxer,
+    events: VecDeque<FlvEvent>,
+    chunk: Box<[u8]>,
+    finished: bool,
+}
+
+impl<R: Read> LenientEvents<R> {
+    fn next_event(&mut self) -> Result<Option<FlvEvent>, FlvError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+
+            if self.finished {
+                return Ok(None);
+            }
+
+            let n = match self.stream.read(&mut self.chunk) {
+                Ok(n) => n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            let events = if n == 0 {
+                self.finished = true;
+                self.demuxer.finish()?
+            } else {
+                self.demuxer.push(&self.chunk[..n])?
+            };
+
+            self.events.extend(events);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::{
+    error::{ErrorContext, FlvError},
+    v1::{
+        header::FlvHeader,
+        limits::DecodeLimits,
+        read_u32_be,
+        tag::{FLV_TAG_HEADER_SIZE, FlvTagType, tag_type_of},
+        timestamp::FlvTimestamp,
+        video::{AvcPacketType, CodecId, FrameType},
+    },
+};
+
+/// What the header of a tag tells without reading its data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagInfo {
+    /// Offset of the tag from the start of the file
+    pub offset: u64,
+
+    pub tag_type: FlvTagType,
+
+    pub timestamp: FlvTimestamp,
+
+    /// U24; len of the data, without the tag header and the previous tag size
+    pub data_size: u32,
+
+    /// Video keyframe to seek to, AVC sequence headers are not
+    pub keyframe: bool,
+}
+
+impl TagInfo {
+    /// Tag header + data + PreviousTagSize
+    pub const fn encoded_len(&self) -> u64 {
+        (FLV_TAG_HEADER_SIZE + 4) as u64 + self.data_size as u64
+    }
+}
+
+/// FlvScanner: Reads only the header of every tag (plus the FrameType/CodecId and AVCPacketType
+/// bytes of video) and seeks past the rest, so nothing is decoded or allocated per tag
+///
+/// It is meant to index many files quickly (duration, tag counts, keyframes), use `FlvReader` to
+/// get the data of the tags. The iterator stops after the first error
+pub struct FlvScanner<R: Read + Seek> {
+    stream: R,
+    header: FlvHeader,
+    finished: bool,
+
+    /// Index of the next tag
+    index: u64,
+
+    /// Offset of the next tag from the start of the file
+    offset: u64,
+
+    /// Length of the file, a tag that goes past it is truncated
+    len: u64,
+}
+
+impl<R: Read + Seek> FlvScanner<R> {
+    /// Decodes the FLV header and the `PreviousTagSize0`, the file starts at the current position
+    /// of `stream`
+    pub fn new(stream: R) -> Result<Self, FlvError> {
+        Self::new_with_limits(stream, DecodeLimits::default())
+    }
+
+    /// Same as `new`, the extra bytes of the header must be within `limits`
+    pub fn new_with_limits(mut stream: R, limits: DecodeLimits) -> Result<Self, FlvError> {
+        let start = stream.stream_position()?;
+        let len = stream.seek(SeekFrom::End(0))?.saturating_sub(start);
+        stream.seek(SeekFrom::Start(start))?;
+
+        let header = FlvHeader::decode_with_limits(&mut stream, &limits)?;
+
+        if read_u32_be(&mut stream).in_field("previous tag size")? != 0 {
+            return Err(FlvError::InvalidFile);
+        }
+
+        let offset = header.data_offset as u64 + 4;
+
+        Ok(Self {
+            stream,
+            header,
+            finished: false,
+            index: 0,
+            offset,
+            len,
+        })
+    }
+
+    pub fn header(&self) -> &FlvHeader {
+        &self.header
+    }
+
+    pub fn into_parts(self) -> (FlvHeader, R) {
+        (self.header, self.stream)
+    }
+
+    /// Returns `Ok(None)` if the file ends right before the tag
+    fn next_tag(&mut self) -> Result<Option<TagInfo>, FlvError> {
+        let (index, offset) = (self.index, self.offset);
+        let mut header = [0_u8; FLV_TAG_HEADER_SIZE];
+
+        let got = read_full(&mut self.stream, &mut header)
+            .map_err(|e| e.in_field("tag header").in_tag(index, offset, None))?;
+
+        if got == 0 {
+            return Ok(None);
+        }
+
+        let tag_type = tag_type_of(header[0]);
+        let context = |e: FlvError| e.in_tag(index, offset, Some(tag_type));
+
+        let data_size = BigEndian::read_u24(&header[1..4]);
+        let timestamp = FlvTimestamp::from_parts(BigEndian::read_u24(&header[4..7]), header[7]);
+
+        let info = TagInfo {
+            offset: self.offset,
+            tag_type,
+            timestamp,
+            data_size,
+            keyframe: false,
+        };
+
+        let available = self.len.saturating_sub(self.offset);
+
+        if info.encoded_len() > available {
+            return Err(context(FlvError::Truncated {
+                expected: info.encoded_len() as usize,
+                got: available as usize,
+            }));
+        }
+
+        // FrameType/CodecId (1) + AVCPacketType (1), the rest of the data is skipped
+        let mut buf = [0_u8; 2];
+        let flags = match tag_type {
+            FlvTagType::Video => {
+                let size = (data_size as usize).min(buf.len());
+
+                read_full(&mut self.stream, &mut buf[..size]).map_err(context)?;
+                &buf[..size]
+            }
+            _ => &buf[..0],
+        };
+
+        let skip = data_size as i64 - flags.len() as i64 + 4;
+        self.stream
+            .seek(SeekFrom::Current(skip))
+            .map_err(|e| context(e.into()))?;
+
+        Ok(Some(TagInfo {
+            keyframe: is_keyframe(flags),
+            ..info
+        }))
+    }
+}
+
+impl<R: Read + Seek> Iterator for FlvScanner<R> {
+    type Item = Result<TagInfo, FlvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let tag = self.next_tag().transpose();
+
+        match &tag {
+            Some(Ok(tag)) => {
+                self.index += 1;
+                self.offset += tag.encoded_len();
+            }
+            _ => self.finished = true,
+        }
+
+        tag
+    }
+}
+
+/// `flags` are the first bytes of the data of a video tag
+fn is_keyframe(flags: &[u8]) -> bool {
+    let Some(&frame_codec) = flags.first() else {
+        return false;
+    };
+
+    if FrameType::from(frame_codec >> 4 & 0x0F) != FrameType::Keyframe {
+        return false;
+    }
+
+    // the sequence header is not a frame to seek to
+    match CodecId::from(frame_codec & 0x0F) {
+        CodecId::Avc => flags.get(1) != Some(&AvcPacketType::SEQUENCE_HEADER),
+        _ => true,
+    }
+}
+
+/// Fills `buf`, returns 0 if the stream is at EOF before the first byte, a stream that ends after
+/// it is `Truncated`
+fn read_full<R: Read>(stream: &mut R, buf: &mut [u8]) -> Result<usize, FlvError> {
+    let mut got = 0;
+
+    while got < buf.len() {
+        match stream.read(&mut buf[got..]) {
+            Ok(0) if got == 0 => return Ok(0),
+            Ok(0) => {
+                return Err(FlvError::Truncated {
+                    expected: buf.len(),
+                    got,
+                });
+            }
+            Ok(n) => got += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(got)
+}
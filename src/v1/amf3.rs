@@ -0,0 +1,852 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    string::FromUtf8Error,
+};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use thiserror::Error;
+
+use crate::v1::limits::DecodeBudget;
+
+// AMF3, reached from AMF0 through the `avmplus-object` marker
+
+// NOTE FOR CODE READERS: every value encoded or decoded on its own has its own reference tables,
+// strings and traits are resolved, references to objects are kept as `Amf3Reference`
+
+/// Max value of a U29
+const AMF3_U29_MAX: u32 = 0x1FFF_FFFF;
+
+/// Max length of a string, array or byte array, the U29 is shared with the inline flag
+const AMF3_MAX_LEN: u32 = AMF3_U29_MAX >> 1;
+
+/// Range of an integer, a S29, others are written as a double
+const AMF3_INTEGER_MIN: i32 = -(1 << 28);
+const AMF3_INTEGER_MAX: i32 = (1 << 28) - 1;
+
+/// Flex classes whose externalizable data is one AMF3 value, the data of other classes can't be
+/// read without knowing the class
+const AMF3_EXTERNALIZABLE_VALUE: [&str; 3] = [
+    "flex.messaging.io.ArrayCollection",
+    "flex.messaging.io.ObjectProxy",
+    "mx.collections.ArrayList",
+];
+
+#[derive(Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive, Clone, Copy)]
+#[repr(u8)]
+pub enum Amf3Marker {
+    Undefined = 0x00,
+    Null = 0x01,
+    False = 0x02,
+    True = 0x03,
+    Integer = 0x04,
+    Double = 0x05,
+    String = 0x06,
+    XmlDocument = 0x07,
+    Date = 0x08,
+    Array = 0x09,
+    Object = 0x0A,
+    Xml = 0x0B,
+    ByteArray = 0x0C,
+    VectorInt = 0x0D,
+    VectorUint = 0x0E,
+    VectorDouble = 0x0F,
+    VectorObject = 0x10,
+    Dictionary = 0x11,
+}
+
+impl Amf3Marker {
+    /// Values of the object reference table, they can be sent as a reference
+    pub const fn is_complex(self) -> bool {
+        !matches!(
+            self,
+            Self::Undefined
+                | Self::Null
+                | Self::False
+                | Self::True
+                | Self::Integer
+                | Self::Double
+                | Self::String
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Amf3Value {
+    Undefined,
+    Null,
+    Bool(bool),
+    /// S29, a value out of its range is encoded as a `Double`
+    Integer(i32),
+    Double(f64),
+    String(String),
+    XmlDocument(String),
+    /// Milliseconds since the Unix epoch, UTC
+    Date(f64),
+    Array(Amf3Array),
+    Object(Amf3Object),
+    Xml(String),
+    ByteArray(Vec<u8>),
+    VectorInt(Amf3Vector<i32>),
+    VectorUint(Amf3Vector<u32>),
+    VectorDouble(Amf3Vector<f64>),
+    VectorObject(Amf3ObjectVector),
+    Dictionary(Amf3Dictionary),
+    Reference(Amf3Reference),
+}
+
+/// Index in the object table of a complex value sent before it, kept as is without resolving it
+/// (objects can reference themselves)
+#[derive(Debug, Clone, Copy)]
+pub struct Amf3Reference {
+    /// Marker of the value referenced
+    pub marker: Amf3Marker,
+
+    /// Complex values are counted in the order they start, from 0
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Amf3Array {
+    /// Associative part, sent before the dense part
+    pub assoc: Vec<(String, Amf3Value)>,
+    pub dense: Vec<Amf3Value>,
+}
+
+/// Class of an object, sent once and then referenced by the objects of the same class
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Amf3Traits {
+    /// Empty for anonymous objects
+    pub class_name: String,
+
+    /// The object has dynamic members after the sealed ones
+    pub dynamic: bool,
+
+    /// The data of the object is written by the class itself
+    pub externalizable: bool,
+
+    /// Names of the sealed members
+    pub sealed: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Amf3Object {
+    pub traits: Amf3Traits,
+
+    /// Values of the sealed members, in the order of `traits.sealed`
+    pub sealed: Vec<Amf3Value>,
+
+    /// Only if `traits.dynamic`
+    pub dynamic: Vec<(String, Amf3Value)>,
+
+    /// Data of an externalizable object, only supported for the flex collections whose data is
+    /// one AMF3 value
+    pub external: Option<Box<Amf3Value>>,
+
+    /// How the traits of a decoded object were sent, to write them the same way, the traits of
+    /// other objects are written as a reference if they were already sent
+    traits_sent: Option<TraitsSent>,
+}
+
+/// Traits sent inline or as a reference to the traits table
+#[derive(Debug, Clone, Copy)]
+enum TraitsSent {
+    Inline,
+    Reference(u32),
+}
+
+impl Amf3Object {
+    /// Anonymous dynamic object, the object of AMF3 script data
+    pub fn new(dynamic: Vec<(String, Amf3Value)>) -> Self {
+        Self {
+            traits: Amf3Traits {
+                dynamic: true,
+                ..Default::default()
+            },
+            dynamic,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Amf3Vector<T> {
+    /// The length of the vector can't change
+    pub fixed: bool,
+    pub items: Vec<T>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Amf3ObjectVector {
+    /// The length of the vector can't change
+    pub fixed: bool,
+
+    /// Class of the items, `*` for any
+    pub type_name: String,
+    pub items: Vec<Amf3Value>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Amf3Dictionary {
+    /// The keys are weak references
+    pub weak_keys: bool,
+    pub entries: Vec<(Amf3Value, Amf3Value)>,
+}
+
+impl Amf3Value {
+    /// Bytes written by `encode`
+    pub fn encoded_len(&self) -> usize {
+        let mut counter = ByteCounter(0);
+
+        // an invalid value is counted up to where `encode` fails
+        let _ = self.encode(&mut counter);
+
+        counter.0
+    }
+    /// Repeated strings and traits are written as references
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf3Error> {
+        Encoder {
+            stream,
+            strings: HashMap::new(),
+            traits: Vec::new(),
+        }
+        .value(self)
+    }
+    pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf3Error> {
+        Self::decode_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn decode_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf3Error> {
+        Decoder {
+            stream,
+            budget,
+            strings: Vec::new(),
+            traits: Vec::new(),
+            objects: 0,
+        }
+        .value()
+    }
+
+    /// Marker written for the value
+    fn marker(&self) -> Amf3Marker {
+        match self {
+            Self::Undefined => Amf3Marker::Undefined,
+            Self::Null => Amf3Marker::Null,
+            Self::Bool(false) => Amf3Marker::False,
+            Self::Bool(true) => Amf3Marker::True,
+            Self::Integer(n) if (AMF3_INTEGER_MIN..=AMF3_INTEGER_MAX).contains(n) => {
+                Amf3Marker::Integer
+            }
+            Self::Integer(_) | Self::Double(_) => Amf3Marker::Double,
+            Self::String(_) => Amf3Marker::String,
+            Self::XmlDocument(_) => Amf3Marker::XmlDocument,
+            Self::Date(_) => Amf3Marker::Date,
+            Self::Array(_) => Amf3Marker::Array,
+            Self::Object(_) => Amf3Marker::Object,
+            Self::Xml(_) => Amf3Marker::Xml,
+            Self::ByteArray(_) => Amf3Marker::ByteArray,
+            Self::VectorInt(_) => Amf3Marker::VectorInt,
+            Self::VectorUint(_) => Amf3Marker::VectorUint,
+            Self::VectorDouble(_) => Amf3Marker::VectorDouble,
+            Self::VectorObject(_) => Amf3Marker::VectorObject,
+            Self::Dictionary(_) => Amf3Marker::Dictionary,
+            Self::Reference(reference) => reference.marker,
+        }
+    }
+}
+
+/// U29: 1 to 4 bytes, the first 3 with 7 bits and a continuation bit, the 4th with 8 bits
+fn read_u29<R: Read>(stream: &mut R) -> Result<u32, Amf3Error> {
+    let mut value = 0;
+
+    for _ in 0..3 {
+        let byte = stream.read_u8()?;
+
+        value = value << 7 | (byte & 0x7F) as u32;
+
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Ok(value << 8 | stream.read_u8()? as u32)
+}
+
+fn write_u29<W: Write>(stream: &mut W, value: u32) -> Result<(), Amf3Error> {
+    match value {
+        0..0x80 => stream.write_u8(value as u8)?,
+        0x80..0x4000 => stream.write_all(&[(value >> 7) as u8 | 0x80, value as u8 & 0x7F])?,
+        0x4000..0x20_0000 => stream.write_all(&[
+            (value >> 14) as u8 | 0x80,
+            (value >> 7) as u8 | 0x80,
+            value as u8 & 0x7F,
+        ])?,
+        0x20_0000..=AMF3_U29_MAX => stream.write_all(&[
+            (value >> 22) as u8 | 0x80,
+            (value >> 15) as u8 | 0x80,
+            (value >> 8) as u8 | 0x80,
+            value as u8,
+        ])?,
+        _ => return Err(Amf3Error::TooLong(value as usize)),
+    }
+
+    Ok(())
+}
+
+/// Header of an inline value: its length and the inline flag
+fn inline_header(len: usize) -> Result<u32, Amf3Error> {
+    match u32::try_from(len) {
+        Ok(len) if len <= AMF3_MAX_LEN => Ok(len << 1 | 1),
+        _ => Err(Amf3Error::TooLong(len)),
+    }
+}
+
+/// Counts the bytes written, `encoded_len` encodes into it
+struct ByteCounter(usize);
+
+impl Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Reference tables of the value being decoded
+struct Decoder<'a, R> {
+    stream: &'a mut R,
+    budget: &'a mut DecodeBudget,
+    strings: Vec<String>,
+    traits: Vec<Amf3Traits>,
+
+    /// Complex values started so far, references to them are checked against it
+    objects: u32,
+}
+
+impl<R: Read> Decoder<'_, R> {
+    fn value(&mut self) -> Result<Amf3Value, Amf3Error> {
+        let byte = self.stream.read_u8()?;
+        let marker = Amf3Marker::try_from(byte).map_err(|_| Amf3Error::InvalidMarker(byte))?;
+
+        let value = match marker {
+            Amf3Marker::Undefined => Amf3Value::Undefined,
+            Amf3Marker::Null => Amf3Value::Null,
+            Amf3Marker::False => Amf3Value::Bool(false),
+            Amf3Marker::True => Amf3Value::Bool(true),
+            Amf3Marker::Integer => {
+                // sign extension of the S29
+                let value = read_u29(self.stream)?;
+                Amf3Value::Integer(((value << 3) as i32) >> 3)
+            }
+            Amf3Marker::Double => Amf3Value::Double(self.stream.read_f64::<BigEndian>()?),
+            Amf3Marker::String => Amf3Value::String(self.string()?),
+            marker => {
+                self.budget.enter()?;
+                let value = self.complex(marker)?;
+                self.budget.exit();
+
+                value
+            }
+        };
+
+        Ok(value)
+    }
+
+    /// Reads `len` bytes, a stream that ends before is `Truncated`
+    fn bytes(&mut self, len: usize) -> Result<Vec<u8>, Amf3Error> {
+        self.budget.alloc(len)?;
+
+        let mut data = Vec::with_capacity(len);
+
+        let got = self.stream.take(len as u64).read_to_end(&mut data)?;
+
+        if got < len {
+            return Err(Amf3Error::Truncated { expected: len, got });
+        }
+
+        Ok(data)
+    }
+
+    fn utf8(&mut self, len: usize) -> Result<String, Amf3Error> {
+        self.budget.string(len)?;
+
+        Ok(String::from_utf8(self.bytes(len)?)?)
+    }
+
+    /// A reference to the string table or an inline string, the empty string is never added to
+    /// the table
+    fn string(&mut self) -> Result<String, Amf3Error> {
+        let header = read_u29(self.stream)?;
+
+        if header & 1 == 0 {
+            let index = header >> 1;
+            let string = self
+                .strings
+                .get(index as usize)
+                .ok_or(Amf3Error::InvalidReference(index))?;
+
+            // every reference is a new copy of the string
+            self.budget.string(string.len())?;
+
+            return Ok(string.clone());
+        }
+
+        let string = self.utf8((header >> 1) as usize)?;
+
+        if !string.is_empty() {
+            self.strings.push(string.clone());
+        }
+
+        Ok(string)
+    }
+
+    /// Values of the object table, `header` has the inline flag in bit 0, a reference otherwise
+    fn complex(&mut self, marker: Amf3Marker) -> Result<Amf3Value, Amf3Error> {
+        let header = read_u29(self.stream)?;
+
+        if header & 1 == 0 {
+            let index = header >> 1;
+
+            if index >= self.objects {
+                return Err(Amf3Error::InvalidReference(index));
+            }
+
+            return Ok(Amf3Value::Reference(Amf3Reference { marker, index }));
+        }
+
+        // it is added before its members, so they can reference it
+        self.objects += 1;
+
+        let header = header >> 1;
+
+        let value = match marker {
+            Amf3Marker::XmlDocument => Amf3Value::XmlDocument(self.utf8(header as usize)?),
+            Amf3Marker::Date => Amf3Value::Date(self.stream.read_f64::<BigEndian>()?),
+            Amf3Marker::Array => Amf3Value::Array(self.array(header)?),
+            Amf3Marker::Object => Amf3Value::Object(self.object(header)?),
+            Amf3Marker::Xml => Amf3Value::Xml(self.utf8(header as usize)?),
+            Amf3Marker::ByteArray => Amf3Value::ByteArray(self.bytes(header as usize)?),
+            Amf3Marker::VectorInt => {
+                Amf3Value::VectorInt(self.vector(header, |s| s.read_i32::<BigEndian>())?)
+            }
+            Amf3Marker::VectorUint => {
+                Amf3Value::VectorUint(self.vector(header, |s| s.read_u32::<BigEndian>())?)
+            }
+            Amf3Marker::VectorDouble => {
+                Amf3Value::VectorDouble(self.vector(header, |s| s.read_f64::<BigEndian>())?)
+            }
+            Amf3Marker::VectorObject => Amf3Value::VectorObject(self.object_vector(header)?),
+            Amf3Marker::Dictionary => Amf3Value::Dictionary(self.dictionary(header)?),
+            marker => return Err(Amf3Error::InvalidMarker(marker.into())),
+        };
+
+        Ok(value)
+    }
+
+    /// Name/value pairs until an empty name
+    fn pairs(&mut self) -> Result<Vec<(String, Amf3Value)>, Amf3Error> {
+        let mut pairs = Vec::new();
+
+        loop {
+            let name = self.string()?;
+
+            if name.is_empty() {
+                return Ok(pairs);
+            }
+
+            self.budget.element::<(String, Amf3Value)>(pairs.len())?;
+
+            pairs.push((name, self.value()?));
+        }
+    }
+
+    fn values(&mut self, len: u32) -> Result<Vec<Amf3Value>, Amf3Error> {
+        self.budget.array(len as usize)?;
+
+        // `len` comes from the input, so nothing is reserved with it
+        let mut values = Vec::new();
+
+        for index in 0..len as usize {
+            self.budget.element::<Amf3Value>(index)?;
+            values.push(self.value()?);
+        }
+
+        Ok(values)
+    }
+
+    fn array(&mut self, dense_len: u32) -> Result<Amf3Array, Amf3Error> {
+        let assoc = self.pairs()?;
+        let dense = self.values(dense_len)?;
+
+        Ok(Amf3Array { assoc, dense })
+    }
+
+    /// `header` bit 0 clear is a reference to the traits table, else bit 1 is externalizable,
+    /// bit 2 dynamic and the rest the number of sealed members
+    fn traits(&mut self, header: u32) -> Result<(Amf3Traits, TraitsSent), Amf3Error> {
+        if header & 1 == 0 {
+            let index = header >> 1;
+            let traits = self
+                .traits
+                .get(index as usize)
+                .ok_or(Amf3Error::InvalidReference(index))?;
+
+            self.budget
+                .alloc(traits.sealed.iter().map(String::len).sum())?;
+
+            return Ok((traits.clone(), TraitsSent::Reference(index)));
+        }
+
+        let externalizable = header & 2 != 0;
+        let dynamic = header & 4 != 0;
+        let len = header >> 3;
+
+        let class_name = self.string()?;
+
+        self.budget.array(len as usize)?;
+
+        let mut sealed = Vec::new();
+
+        for index in 0..len as usize {
+            self.budget.element::<String>(index)?;
+            sealed.push(self.string()?);
+        }
+
+        let traits = Amf3Traits {
+            class_name,
+            dynamic,
+            externalizable,
+            sealed,
+        };
+
+        self.traits.push(traits.clone());
+
+        Ok((traits, TraitsSent::Inline))
+    }
+
+    fn object(&mut self, header: u32) -> Result<Amf3Object, Amf3Error> {
+        let (traits, traits_sent) = self.traits(header)?;
+
+        if traits.externalizable {
+            if !AMF3_EXTERNALIZABLE_VALUE.contains(&traits.class_name.as_str()) {
+                return Err(Amf3Error::UnsupportedExternalizable(traits.class_name));
+            }
+
+            let external = Box::new(self.value()?);
+
+            return Ok(Amf3Object {
+                traits,
+                external: Some(external),
+                traits_sent: Some(traits_sent),
+                ..Default::default()
+            });
+        }
+
+        let sealed = self.values(traits.sealed.len() as u32)?;
+
+        let dynamic = if traits.dynamic {
+            self.pairs()?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Amf3Object {
+            traits,
+            sealed,
+            dynamic,
+            external: None,
+            traits_sent: Some(traits_sent),
+        })
+    }
+
+    fn vector<T>(
+        &mut self,
+        len: u32,
+        read: impl Fn(&mut R) -> io::Result<T>,
+    ) -> Result<Amf3Vector<T>, Amf3Error> {
+        let fixed = self.stream.read_u8()? != 0;
+
+        self.budget.array(len as usize)?;
+
+        let mut items = Vec::new();
+
+        for index in 0..len as usize {
+            self.budget.element::<T>(index)?;
+            items.push(read(self.stream)?);
+        }
+
+        Ok(Amf3Vector { fixed, items })
+    }
+
+    fn object_vector(&mut self, len: u32) -> Result<Amf3ObjectVector, Amf3Error> {
+        let fixed = self.stream.read_u8()? != 0;
+        let type_name = self.string()?;
+        let items = self.values(len)?;
+
+        Ok(Amf3ObjectVector {
+            fixed,
+            type_name,
+            items,
+        })
+    }
+
+    fn dictionary(&mut self, len: u32) -> Result<Amf3Dictionary, Amf3Error> {
+        let weak_keys = self.stream.read_u8()? != 0;
+
+        self.budget.array(len as usize)?;
+
+        let mut entries = Vec::new();
+
+        for index in 0..len as usize {
+            self.budget.element::<(Amf3Value, Amf3Value)>(index)?;
+            entries.push((self.value()?, self.value()?));
+        }
+
+        Ok(Amf3Dictionary { weak_keys, entries })
+    }
+}
+
+/// Reference tables of the value being encoded, complex values are always written inline
+struct Encoder<'a, 'v, W> {
+    stream: &'a mut W,
+    strings: HashMap<&'v str, u32>,
+    traits: Vec<&'v Amf3Traits>,
+}
+
+impl<'v, W: Write> Encoder<'_, 'v, W> {
+    fn value(&mut self, value: &'v Amf3Value) -> Result<(), Amf3Error> {
+        let marker = value.marker();
+
+        self.stream.write_u8(marker.into())?;
+
+        match value {
+            Amf3Value::Undefined | Amf3Value::Null | Amf3Value::Bool(_) => {}
+            Amf3Value::Integer(n) if marker == Amf3Marker::Integer => {
+                write_u29(self.stream, *n as u32 & AMF3_U29_MAX)?;
+            }
+            Amf3Value::Integer(n) => self.stream.write_f64::<BigEndian>(*n as f64)?,
+            Amf3Value::Double(n) => self.stream.write_f64::<BigEndian>(*n)?,
+            Amf3Value::String(string) => self.string(string)?,
+            Amf3Value::XmlDocument(string) | Amf3Value::Xml(string) => self.utf8(string)?,
+            Amf3Value::Date(millis) => {
+                write_u29(self.stream, 1)?;
+                self.stream.write_f64::<BigEndian>(*millis)?;
+            }
+            Amf3Value::Array(array) => self.array(array)?,
+            Amf3Value::Object(object) => self.object(object)?,
+            Amf3Value::ByteArray(data) => {
+                write_u29(self.stream, inline_header(data.len())?)?;
+                self.stream.write_all(data)?;
+            }
+            Amf3Value::VectorInt(vector) => {
+                self.vector(vector, |s, n| s.write_i32::<BigEndian>(*n))?
+            }
+            Amf3Value::VectorUint(vector) => {
+                self.vector(vector, |s, n| s.write_u32::<BigEndian>(*n))?
+            }
+            Amf3Value::VectorDouble(vector) => {
+                self.vector(vector, |s, n| s.write_f64::<BigEndian>(*n))?
+            }
+            Amf3Value::VectorObject(vector) => {
+                write_u29(self.stream, inline_header(vector.items.len())?)?;
+                self.stream.write_u8(vector.fixed as u8)?;
+                self.string(&vector.type_name)?;
+
+                for item in &vector.items {
+                    self.value(item)?;
+                }
+            }
+            Amf3Value::Dictionary(dictionary) => {
+                write_u29(self.stream, inline_header(dictionary.entries.len())?)?;
+                self.stream.write_u8(dictionary.weak_keys as u8)?;
+
+                for (key, value) in &dictionary.entries {
+                    self.value(key)?;
+                    self.value(value)?;
+                }
+            }
+            Amf3Value::Reference(reference) => {
+                if !reference.marker.is_complex() {
+                    return Err(Amf3Error::InvalidMarker(reference.marker.into()));
+                }
+
+                if reference.index > AMF3_MAX_LEN {
+                    return Err(Amf3Error::InvalidReference(reference.index));
+                }
+
+                write_u29(self.stream, reference.index << 1)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Inline string, not added to the string table
+    fn utf8(&mut self, string: &str) -> Result<(), Amf3Error> {
+        write_u29(self.stream, inline_header(string.len())?)?;
+        self.stream.write_all(string.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn string(&mut self, string: &'v str) -> Result<(), Amf3Error> {
+        if let Some(&index) = self.strings.get(string) {
+            return write_u29(self.stream, index << 1);
+        }
+
+        if !string.is_empty() {
+            self.strings.insert(string, self.strings.len() as u32);
+        }
+
+        self.utf8(string)
+    }
+
+    /// Name/value pairs and the empty name that ends them
+    fn pairs(&mut self, pairs: &'v [(String, Amf3Value)]) -> Result<(), Amf3Error> {
+        for (name, value) in pairs {
+            // an empty name would end the pairs
+            if name.is_empty() {
+                return Err(Amf3Error::EmptyKey);
+            }
+
+            self.string(name)?;
+            self.value(value)?;
+        }
+
+        self.string("")
+    }
+
+    fn array(&mut self, array: &'v Amf3Array) -> Result<(), Amf3Error> {
+        write_u29(self.stream, inline_header(array.dense.len())?)?;
+
+        self.pairs(&array.assoc)?;
+
+        for value in &array.dense {
+            self.value(value)?;
+        }
+
+        Ok(())
+    }
+
+    fn object(&mut self, object: &'v Amf3Object) -> Result<(), Amf3Error> {
+        let traits = &object.traits;
+
+        if !traits.externalizable && object.sealed.len() != traits.sealed.len() {
+            return Err(Amf3Error::SealedMembers {
+                expected: traits.sealed.len(),
+                got: object.sealed.len(),
+            });
+        }
+
+        // a decoded object keeps its reference while the table holds the same traits, the traits
+        // it sent inline are sent inline again
+        let index = match object.traits_sent {
+            Some(TraitsSent::Reference(index))
+                if self.traits.get(index as usize) == Some(&traits) =>
+            {
+                Some(index as usize)
+            }
+            Some(TraitsSent::Inline) => None,
+            _ => self.traits.iter().position(|t| *t == traits),
+        };
+
+        if let Some(index) = index {
+            write_u29(self.stream, (index as u32) << 2 | 0b01)?;
+        } else {
+            let len = u32::try_from(traits.sealed.len())
+                .ok()
+                .filter(|len| *len <= AMF3_U29_MAX >> 4)
+                .ok_or(Amf3Error::TooLong(traits.sealed.len()))?;
+
+            let flags = (traits.dynamic as u32) << 3 | (traits.externalizable as u32) << 2;
+
+            write_u29(self.stream, len << 4 | flags | 0b11)?;
+
+            self.traits.push(traits);
+
+            self.string(&traits.class_name)?;
+
+            for name in &traits.sealed {
+                self.string(name)?;
+            }
+        }
+
+        if traits.externalizable {
+            let external = object
+                .external
+                .as_deref()
+                .ok_or_else(|| Amf3Error::UnsupportedExternalizable(traits.class_name.clone()))?;
+
+            return self.value(external);
+        }
+
+        for value in &object.sealed {
+            self.value(value)?;
+        }
+
+        if traits.dynamic {
+            self.pairs(&object.dynamic)?;
+        }
+
+        Ok(())
+    }
+
+    fn vector<T>(
+        &mut self,
+        vector: &Amf3Vector<T>,
+        write: impl Fn(&mut W, &T) -> io::Result<()>,
+    ) -> Result<(), Amf3Error> {
+        write_u29(self.stream, inline_header(vector.items.len())?)?;
+        self.stream.write_u8(vector.fixed as u8)?;
+
+        for item in &vector.items {
+            write(self.stream, item)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Amf3Error {
+    #[error("Invalid marker: {0:#04x}")]
+    InvalidMarker(u8),
+
+    /// A string, traits or object reference past the end of its table
+    #[error("Invalid reference: {0}")]
+    InvalidReference(u32),
+
+    /// A length that doesn't fit in its U29
+    #[error("Too long: {0}")]
+    TooLong(usize),
+
+    /// The externalizable data of the class can't be read or written without knowing the class
+    #[error("Unsupported externalizable class: {0}")]
+    UnsupportedExternalizable(String),
+
+    #[error("Sealed members: expected {expected}, got {got}")]
+    SealedMembers { expected: usize, got: usize },
+
+    /// A member name can't be empty, it ends the members
+    #[error("Empty key")]
+    EmptyKey,
+
+    #[error("Utf8Error: {0}")]
+    Utf8Error(#[from] FromUtf8Error),
+
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// A `DecodeLimits` bound, named by the field, was exceeded
+    #[error("Decode limit exceeded: {0}")]
+    LimitExceeded(&'static str),
+
+    #[error("Truncated: expected {expected} bytes, got {got}")]
+    Truncated { expected: usize, got: usize },
+}
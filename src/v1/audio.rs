@@ -1,13 +1,14 @@
 use byteorder::{ReadBytesExt, WriteBytesExt};
-use num_enum::{TryFromPrimitive, IntoPrimitive};
+use num_enum::{FromPrimitive, IntoPrimitive, TryFromPrimitive};
 
-use crate::error::FlvError;
+use crate::{
+    error::{ErrorContext, FlvError},
+    v1::{Payload, payload_from_slice, payload_size, read_payload, take},
+};
 
 const FLV_AUDIO_DATA_HEADER_SIZE: usize = 1;
 
-
-
-#[derive(Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, IntoPrimitive, FromPrimitive, Clone, Copy)]
 #[repr(u8)]
 pub enum SoundFormat {
     LinearPcmPlatformEndian = 0,
@@ -23,6 +24,10 @@ pub enum SoundFormat {
     Speex = 11,
     Mp3_8Khz = 14,
     DeviceSpecific = 15,
+
+    /// Not defined by the spec, the audio data is kept as `AudioData::Other`
+    #[num_enum(catch_all)]
+    Unknown(u8),
 }
 
 #[derive(Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive, Clone, Copy)]
@@ -48,7 +53,7 @@ pub enum SoundType {
     Stereo = 1,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FlvAudioTag {
     pub sound_format: SoundFormat,
     pub sound_rate: SoundRate,
@@ -59,25 +64,35 @@ pub struct FlvAudioTag {
 }
 
 impl FlvAudioTag {
-    pub const fn size(&self) -> usize {
-        self.data.size()
+    /// AAC AudioSpecificConfig, it must come before the first frame
+    pub fn is_sequence_header(&self) -> bool {
+        match &self.data {
+            AudioData::Aac(aac) => aac.packet_type == AacPacketType::SEQUENCE_HEADER,
+            AudioData::Other(_) => false,
+        }
+    }
+    /// SoundFormat/SoundRate/SoundSize/SoundType (1) + audio data
+    pub const fn encoded_len(&self) -> usize {
+        FLV_AUDIO_DATA_HEADER_SIZE + self.data.encoded_len()
     }
     pub fn decode<T: ReadBytesExt>(stream: &mut T, data_size: usize) -> Result<Self, FlvError> {
-        let sound_info = stream.read_u8()?;
+        // the size is checked before reading, so a short tag doesn't read into the next one
+        let data_size =
+            payload_size(data_size, FLV_AUDIO_DATA_HEADER_SIZE).in_field("audio header")?;
+
+        let sound_info = stream.read_u8().in_field("audio header")?;
         let sound_format = (sound_info >> 4) & 0b0000_1111_u8;
         let sound_rate = (sound_info >> 2) & 0b0000_0011_u8;
         let sound_size = (sound_info >> 1) & 0b0000_0001_u8;
         let sound_type = sound_info & 0b0000_0001_u8;
 
-        let data_size = data_size - FLV_AUDIO_DATA_HEADER_SIZE;
-
         let data = AudioData::decode(stream, data_size, sound_format)?;
 
         Ok(Self {
-            sound_format: SoundFormat::try_from(sound_format)?,
-            sound_rate: SoundRate::try_from(sound_rate)?,
-            sound_size: SoundSize::try_from(sound_size)?,
-            sound_type: SoundType::try_from(sound_type)?,
+            sound_format: SoundFormat::from(sound_format),
+            sound_rate: SoundRate::try_from(sound_rate).in_field("audio header")?,
+            sound_size: SoundSize::try_from(sound_size).in_field("audio header")?,
+            sound_type: SoundType::try_from(sound_type).in_field("audio header")?,
             data,
         })
     }
@@ -87,9 +102,7 @@ impl FlvAudioTag {
         let sound_size: u8 = self.sound_size.into();
         let sound_type: u8 = self.sound_type.into();
 
-
-        let sound_info =
-            sound_format << 4 | sound_rate << 2 | sound_size << 1 | sound_type;
+        let sound_info = sound_format << 4 | sound_rate << 2 | sound_size << 1 | sound_type;
 
         stream.write_u8(sound_info)?;
 
@@ -99,16 +112,16 @@ impl FlvAudioTag {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AudioData {
     Aac(AacAudioData),
-    Other(Vec<u8>),
+    Other(Payload),
 }
 
 impl AudioData {
-    pub const fn size(&self) -> usize {
+    pub const fn encoded_len(&self) -> usize {
         match self {
-            AudioData::Aac(aac) => aac.size(),
+            AudioData::Aac(aac) => aac.encoded_len(),
             AudioData::Other(other) => other.len(),
         }
     }
@@ -118,21 +131,17 @@ impl AudioData {
         sound_format: u8,
     ) -> Result<AudioData, FlvError> {
         Ok(match sound_format {
-            10 => AudioData::Aac(AacAudioData::encode(stream, data_size)?),
-            _ => {
-                let mut data = vec![0_u8; data_size];
-                stream.read(&mut data)?;
-                AudioData::Other(data)
-            }
+            10 => AudioData::Aac(AacAudioData::decode(stream, data_size)?),
+            _ => AudioData::Other(read_payload(stream, data_size).in_field("audio data")?),
         })
     }
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), FlvError> {
         match self {
             AudioData::Aac(aac) => {
-                aac.decode(stream)?;
+                aac.encode(stream)?;
             }
             AudioData::Other(raw) => {
-                stream.write(&raw)?;
+                stream.write_all(raw)?;
             }
         }
 
@@ -140,33 +149,134 @@ impl AudioData {
     }
 }
 
-#[derive(Debug)]
+pub struct AacPacketType;
+
+impl AacPacketType {
+    pub const SEQUENCE_HEADER: u8 = 0;
+    pub const RAW: u8 = 1;
+}
+
+#[derive(Debug, Clone)]
 pub struct AacAudioData {
     pub packet_type: u8,
-    pub data: Vec<u8>,
+    pub data: Payload,
 }
 
 impl AacAudioData {
-    pub const fn size(&self) -> usize {
-        self.data.len() + 1
+    /// AACPacketType (1) + data
+    pub const fn encoded_len(&self) -> usize {
+        1 + self.data.len()
     }
-    pub fn encode<T: ReadBytesExt>(
+    pub fn decode<T: ReadBytesExt>(
         stream: &mut T,
         data_size: usize,
     ) -> Result<AacAudioData, FlvError> {
-        let packet_type = stream.read_u8()?;
-        let data_size = data_size - 1;
-
-        let mut data = vec![0_u8; data_size];
+        let data_size = payload_size(data_size, 1).in_field("audio header")?;
+        let packet_type = stream.read_u8().in_field("audio header")?;
 
-        stream.read(&mut data)?;
+        let data = read_payload(stream, data_size).in_field("audio data")?;
 
         Ok(AacAudioData { packet_type, data })
     }
-    pub fn decode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), FlvError> {
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), FlvError> {
         stream.write_u8(self.packet_type)?;
-        stream.write(&self.data)?;
+        stream.write_all(&self.data)?;
 
         Ok(())
     }
 }
+
+/// Borrowed version of `FlvAudioTag`, the payload points into the parsed buffer
+#[derive(Debug, Clone, Copy)]
+pub struct FlvAudioTagRef<'a> {
+    pub sound_format: SoundFormat,
+    pub sound_rate: SoundRate,
+    pub sound_size: SoundSize,
+    pub sound_type: SoundType,
+
+    pub data: AudioDataRef<'a>,
+}
+
+impl<'a> FlvAudioTagRef<'a> {
+    /// `data` must be the whole data of the tag
+    pub fn parse(data: &'a [u8]) -> Result<Self, FlvError> {
+        let mut data = data;
+        let sound_info = take(&mut data, 1).in_field("audio header")?[0];
+        let sound_format = (sound_info >> 4) & 0b0000_1111_u8;
+        let sound_rate = (sound_info >> 2) & 0b0000_0011_u8;
+        let sound_size = (sound_info >> 1) & 0b0000_0001_u8;
+        let sound_type = sound_info & 0b0000_0001_u8;
+
+        let data = AudioDataRef::parse(data, sound_format)?;
+
+        Ok(Self {
+            sound_format: SoundFormat::from(sound_format),
+            sound_rate: SoundRate::try_from(sound_rate).in_field("audio header")?,
+            sound_size: SoundSize::try_from(sound_size).in_field("audio header")?,
+            sound_type: SoundType::try_from(sound_type).in_field("audio header")?,
+            data,
+        })
+    }
+    pub fn to_owned(&self) -> FlvAudioTag {
+        self.owned_with(&payload_from_slice)
+    }
+    /// `payload` turns the borrowed payload into the owned one
+    pub(crate) fn owned_with(&self, payload: &impl Fn(&'a [u8]) -> Payload) -> FlvAudioTag {
+        FlvAudioTag {
+            sound_format: self.sound_format,
+            sound_rate: self.sound_rate,
+            sound_size: self.sound_size,
+            sound_type: self.sound_type,
+            data: self.data.owned_with(payload),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum AudioDataRef<'a> {
+    Aac(AacAudioDataRef<'a>),
+    Other(&'a [u8]),
+}
+
+impl<'a> AudioDataRef<'a> {
+    /// `data` must be the data of the tag after the sound info byte
+    pub fn parse(data: &'a [u8], sound_format: u8) -> Result<Self, FlvError> {
+        Ok(match sound_format {
+            10 => Self::Aac(AacAudioDataRef::parse(data)?),
+            _ => Self::Other(data),
+        })
+    }
+    pub fn to_owned(&self) -> AudioData {
+        self.owned_with(&payload_from_slice)
+    }
+    pub(crate) fn owned_with(&self, payload: &impl Fn(&'a [u8]) -> Payload) -> AudioData {
+        match self {
+            Self::Aac(aac) => AudioData::Aac(aac.owned_with(payload)),
+            Self::Other(data) => AudioData::Other(payload(data)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AacAudioDataRef<'a> {
+    pub packet_type: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> AacAudioDataRef<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, FlvError> {
+        let mut data = data;
+        let packet_type = take(&mut data, 1).in_field("audio header")?[0];
+
+        Ok(Self { packet_type, data })
+    }
+    pub fn to_owned(&self) -> AacAudioData {
+        self.owned_with(&payload_from_slice)
+    }
+    pub(crate) fn owned_with(&self, payload: &impl Fn(&'a [u8]) -> Payload) -> AacAudioData {
+        AacAudioData {
+            packet_type: self.packet_type,
+            data: payload(self.data),
+        }
+    }
+}
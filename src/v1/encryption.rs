@@ -0,0 +1,153 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+
+use crate::{
+    error::{ErrorContext, FlvError},
+    v1::{Payload, advance_payload, read_payload, script::Amf0Key, tag::FLV_TAG_MAX_DATA_SIZE},
+};
+
+/// FilterName of the whole-body AES-128-CBC filter
+pub const FLV_FILTER_ENCRYPTION: &str = "Encryption";
+
+/// FilterName of the selective encryption filter
+pub const FLV_FILTER_SELECTIVE_ENCRYPTION: &str = "SE";
+
+/// Size of the AES-128 IV of the filter params
+pub const FLV_FILTER_IV_SIZE: usize = 16;
+
+/// EncryptionTagHeader + FilterParams: present between the audio/video tag header and the body of
+/// a tag with the Filter bit set, the body that follows is encrypted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncryptionTagHeader {
+    /// UI8; number of filters applied to the tag, always 1
+    pub num_filters: u8,
+
+    pub filter: FilterParams,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterParams {
+    /// "Encryption": the whole body is encrypted with this IV
+    Encryption { iv: [u8; FLV_FILTER_IV_SIZE] },
+
+    /// "SE": only the access units with an IV are encrypted
+    SelectiveEncryption {
+        iv: Option<[u8; FLV_FILTER_IV_SIZE]>,
+    },
+
+    /// A filter not defined by the spec (or malformed params), kept as it is to pass it through
+    Other { name: String, params: Payload },
+}
+
+impl FilterParams {
+    /// FilterName of the filter
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Encryption { .. } => FLV_FILTER_ENCRYPTION,
+            Self::SelectiveEncryption { .. } => FLV_FILTER_SELECTIVE_ENCRYPTION,
+            Self::Other { name, .. } => name,
+        }
+    }
+
+    /// Length of the params, without the filter name
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            Self::Encryption { .. } => FLV_FILTER_IV_SIZE,
+            Self::SelectiveEncryption { iv: Some(_) } => 1 + FLV_FILTER_IV_SIZE,
+            Self::SelectiveEncryption { iv: None } => 1,
+            Self::Other { params, .. } => params.len(),
+        }
+    }
+
+    /// `params` must be exactly the params of the filter `name`
+    fn parse(name: String, params: Payload) -> Self {
+        let iv = |bytes: &[u8]| <[u8; FLV_FILTER_IV_SIZE]>::try_from(bytes).ok();
+
+        // EncryptedAU UB[1] + Reserved UB[7], then the IV only if EncryptedAU is set
+        let filter = match (name.as_str(), &params[..]) {
+            (FLV_FILTER_ENCRYPTION, bytes) => iv(bytes).map(|iv| Self::Encryption { iv }),
+            (FLV_FILTER_SELECTIVE_ENCRYPTION, [flags]) if flags & 0x80 == 0 => {
+                Some(Self::SelectiveEncryption { iv: None })
+            }
+            (FLV_FILTER_SELECTIVE_ENCRYPTION, [flags, bytes @ ..]) if flags & 0x80 != 0 => {
+                iv(bytes).map(|iv| Self::SelectiveEncryption { iv: Some(iv) })
+            }
+            _ => None,
+        };
+
+        filter.unwrap_or(Self::Other { name, params })
+    }
+
+    fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), FlvError> {
+        match self {
+            Self::Encryption { iv } => stream.write_all(iv)?,
+            Self::SelectiveEncryption { iv } => {
+                stream.write_u8((iv.is_some() as u8) << 7)?;
+
+                if let Some(iv) = iv {
+                    stream.write_all(iv)?;
+                }
+            }
+            Self::Other { params, .. } => stream.write_all(params)?,
+        }
+
+        Ok(())
+    }
+}
+
+impl EncryptionTagHeader {
+    pub fn new(filter: FilterParams) -> Self {
+        Self {
+            num_filters: 1,
+            filter,
+        }
+    }
+
+    /// NumFilters (1) + FilterName (2 + N) + Length (3) + FilterParams
+    pub fn encoded_len(&self) -> usize {
+        1 + 2 + self.filter.name().len() + 3 + self.filter.encoded_len()
+    }
+
+    pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, FlvError> {
+        let num_filters = stream.read_u8()?;
+
+        // FilterName is a SCRIPTDATASTRING, the same encoding as an AMF0 key
+        let name = Amf0Key::decode(stream)?.key;
+
+        let length = stream.read_u24::<BigEndian>()?;
+        let params = read_payload(stream, length as usize).in_field("filter params")?;
+
+        Ok(Self {
+            num_filters,
+            filter: FilterParams::parse(name, params),
+        })
+    }
+
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), FlvError> {
+        let name = Amf0Key::new(self.filter.name().to_string())?;
+        let length = self.filter.encoded_len();
+
+        if length > FLV_TAG_MAX_DATA_SIZE as usize {
+            return Err(FlvError::TagTooLarge(length));
+        }
+
+        stream.write_u8(self.num_filters)?;
+        name.encode(stream)?;
+        stream.write_u24::<BigEndian>(length as u32)?;
+        self.filter.encode(stream)?;
+
+        Ok(())
+    }
+}
+
+/// Decodes the `EncryptionTagHeader` at the start of `payload` and removes it, so the payload is
+/// left with the encrypted body
+pub(crate) fn split_encryption_header(
+    payload: &mut Payload,
+) -> Result<EncryptionTagHeader, FlvError> {
+    let mut rest = &payload[..];
+    let header = EncryptionTagHeader::decode(&mut rest)?;
+
+    advance_payload(payload, payload.len() - rest.len());
+
+    Ok(header)
+}
@@ -1,6 +1,9 @@
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 
-use crate::error::FlvError;
+use crate::{
+    error::{ErrorContext, FlvError},
+    v1::{Payload, limits::DecodeLimits, read_payload},
+};
 
 pub const FLV_HEADER_SIGNATURE: u32 = 0x464c56;
 pub const FLV_HEADER_VERSION: u8 = 0x1;
@@ -24,7 +27,12 @@ pub struct FlvHeader {
 
     pub flags: HeaderFlags,
 
+    /// Size of the header, 9 unless the header has `extra` bytes
     pub data_offset: u32,
+
+    /// Bytes between the 9 bytes of the header and `data_offset`, not defined by the spec but kept
+    /// to write them again
+    pub extra: Payload,
 }
 
 impl FlvHeader {
@@ -34,9 +42,45 @@ impl FlvHeader {
             version: FLV_HEADER_VERSION,
             flags,
             data_offset: FLV_HEADER_DATA_OFFSET,
+            extra: Payload::new(),
         }
     }
+    /// Sets the `extra` bytes, `data_offset` is updated to include them
+    pub fn with_extra(mut self, extra: impl Into<Payload>) -> Self {
+        self.extra = extra.into();
+        self.data_offset = FLV_HEADER_DATA_OFFSET + self.extra.len() as u32;
+        self
+    }
+    /// The 9 bytes of the header + `extra`
+    pub fn encoded_len(&self) -> usize {
+        FLV_HEADER_DATA_OFFSET as usize + self.extra.len()
+    }
+    /// Size of the `extra` bytes that follow the 9 bytes of the header
+    pub const fn extra_size(&self) -> usize {
+        self.data_offset.saturating_sub(FLV_HEADER_DATA_OFFSET) as usize
+    }
     pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, FlvError> {
+        Self::decode_with_limits(stream, &DecodeLimits::default())
+    }
+    /// Decodes a header from untrusted input, extra bytes over `limits.max_total_alloc` are an
+    /// error before they are read
+    pub fn decode_with_limits<T: ReadBytesExt>(
+        stream: &mut T,
+        limits: &DecodeLimits,
+    ) -> Result<Self, FlvError> {
+        let fixed = read_payload(stream, FLV_HEADER_DATA_OFFSET as usize)?;
+        let mut header = Self::decode_fixed(&mut &fixed[..])?;
+
+        limits
+            .check_header_extra(header.extra_size())
+            .in_field("header extra")?;
+
+        header.extra = read_payload(stream, header.extra_size()).in_field("header extra")?;
+
+        Ok(header)
+    }
+    /// Decodes the 9 bytes of the header, `extra` is left empty
+    pub(crate) fn decode_fixed<T: ReadBytesExt>(stream: &mut T) -> Result<Self, FlvError> {
         let signature = stream.read_u24::<BigEndian>()?;
 
         if signature != FLV_HEADER_SIGNATURE {
@@ -62,14 +106,21 @@ impl FlvHeader {
             version,
             flags: HeaderFlags::from_bits_truncate(flags),
             data_offset,
+            extra: Payload::new(),
         })
     }
 
+    /// `data_offset` must match the length of `extra`
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), FlvError> {
+        if self.data_offset as usize != self.encoded_len() {
+            return Err(FlvError::InvalidDataOffset);
+        }
+
         stream.write_u24::<BigEndian>(self.signature)?;
         stream.write_u8(self.version)?;
         stream.write_u8(self.flags.bits())?;
         stream.write_u32::<BigEndian>(self.data_offset)?;
+        stream.write_all(&self.extra)?;
 
         Ok(())
     }
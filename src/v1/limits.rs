@@ -0,0 +1,156 @@
+use std::mem::size_of;
+
+use crate::{
+    error::FlvError,
+    v1::{amf3::Amf3Error, script::Amf0Error, tag::FLV_TAG_MAX_DATA_SIZE},
+};
+
+/// DecodeLimits: Bounds on the resources used to decode one tag, input that goes over them fails
+/// with `LimitExceeded` before anything is allocated for it
+///
+/// The defaults accept every tag a real encoder produces while keeping a hostile tag from
+/// allocating more than `max_total_alloc` or nesting deep enough to overflow the stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Max `data_size` of a tag
+    pub max_tag_size: u32,
+
+    /// Max nesting of AMF objects and arrays
+    pub max_amf_depth: usize,
+
+    /// Max length in bytes of an AMF string or key
+    pub max_string_len: usize,
+
+    /// Max number of elements of an AMF object or array
+    pub max_array_len: usize,
+
+    /// Max bytes allocated to decode one tag (payload, strings and AMF values)
+    pub max_total_alloc: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_tag_size: FLV_TAG_MAX_DATA_SIZE,
+            max_amf_depth: 64,
+            max_string_len: FLV_TAG_MAX_DATA_SIZE as usize,
+            max_array_len: 1 << 20,
+            max_total_alloc: 64 * 1024 * 1024,
+        }
+    }
+}
+
+impl DecodeLimits {
+    /// Checks the `data_size` of a tag header before its data is read
+    pub(crate) fn check_tag_size(&self, data_size: u32) -> Result<(), FlvError> {
+        if data_size > self.max_tag_size {
+            return Err(FlvError::LimitExceeded("max_tag_size"));
+        }
+
+        if data_size as usize > self.max_total_alloc {
+            return Err(FlvError::LimitExceeded("max_total_alloc"));
+        }
+
+        Ok(())
+    }
+
+    /// Checks the size of the extra bytes of the FLV header (`data_offset - 9`) before they are
+    /// read
+    pub(crate) fn check_header_extra(&self, extra_size: usize) -> Result<(), FlvError> {
+        if extra_size > self.max_total_alloc {
+            return Err(FlvError::LimitExceeded("max_total_alloc"));
+        }
+
+        Ok(())
+    }
+}
+
+/// A `DecodeLimits` bound, named by the field, was exceeded while decoding AMF0 or AMF3
+#[derive(Debug)]
+pub(crate) struct LimitExceeded(&'static str);
+
+impl From<LimitExceeded> for Amf0Error {
+    fn from(e: LimitExceeded) -> Self {
+        Self::LimitExceeded(e.0)
+    }
+}
+
+impl From<LimitExceeded> for Amf3Error {
+    fn from(e: LimitExceeded) -> Self {
+        Self::LimitExceeded(e.0)
+    }
+}
+
+/// What has been used of the limits while decoding the AMF values of a tag
+#[derive(Debug)]
+pub(crate) struct DecodeBudget {
+    limits: DecodeLimits,
+    depth: usize,
+    allocated: usize,
+}
+
+impl Default for DecodeBudget {
+    fn default() -> Self {
+        Self::new(DecodeLimits::default())
+    }
+}
+
+impl DecodeBudget {
+    pub(crate) fn new(limits: DecodeLimits) -> Self {
+        Self {
+            limits,
+            depth: 0,
+            allocated: 0,
+        }
+    }
+
+    /// Accounts `bytes` more allocated
+    pub(crate) fn alloc(&mut self, bytes: usize) -> Result<(), LimitExceeded> {
+        self.allocated = self.allocated.saturating_add(bytes);
+
+        if self.allocated > self.limits.max_total_alloc {
+            return Err(LimitExceeded("max_total_alloc"));
+        }
+
+        Ok(())
+    }
+
+    /// Accounts a string of `len` bytes, before it is read
+    pub(crate) fn string(&mut self, len: usize) -> Result<(), LimitExceeded> {
+        if len > self.limits.max_string_len {
+            return Err(LimitExceeded("max_string_len"));
+        }
+
+        self.alloc(len)
+    }
+
+    /// Accounts an array of `len` elements, elements are accounted one by one with `element`
+    pub(crate) fn array(&self, len: usize) -> Result<(), LimitExceeded> {
+        if len > self.limits.max_array_len {
+            return Err(LimitExceeded("max_array_len"));
+        }
+
+        Ok(())
+    }
+
+    /// Accounts the element `index` of an array of `T`
+    pub(crate) fn element<T>(&mut self, index: usize) -> Result<(), LimitExceeded> {
+        self.array(index + 1)?;
+        self.alloc(size_of::<T>())
+    }
+
+    /// Enters a nested object or array, `exit` must be called after it is decoded
+    pub(crate) fn enter(&mut self) -> Result<(), LimitExceeded> {
+        if self.depth >= self.limits.max_amf_depth {
+            return Err(LimitExceeded("max_amf_depth"));
+        }
+
+        self.depth += 1;
+
+        Ok(())
+    }
+
+    pub(crate) fn exit(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
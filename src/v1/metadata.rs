@@ -0,0 +1,492 @@
+use thiserror::Error;
+
+use crate::v1::{
+    audio::SoundFormat,
+    script::{
+        Amf0Bool, Amf0DataObjectProp, Amf0EcmaArray, Amf0Error, Amf0Key, Amf0LongString,
+        Amf0Number, Amf0Object, Amf0StrictArray, Amf0String, Amf0Value, FLV_SCRIPT_ON_METADATA,
+        FLV_SCRIPT_SET_DATA_FRAME, FlvScriptTag,
+    },
+    video::CodecId,
+};
+
+/// OnMetaData: The props of `onMetaData` with the standard keys typed, times are in seconds and
+/// rates in kbps as encoders write them
+///
+/// A standard key with a null value is taken as missing and kept in `extra` with the other keys,
+/// `to_props` writes the props back in the order they came in
+#[derive(Debug, Clone, Default)]
+pub struct OnMetaData {
+    pub duration: Option<f64>,
+    pub width: Option<f64>,
+    pub height: Option<f64>,
+    pub video_data_rate: Option<f64>,
+    pub frame_rate: Option<f64>,
+    pub video_codec_id: Option<MetadataCodecId>,
+    pub audio_data_rate: Option<f64>,
+    pub audio_sample_rate: Option<f64>,
+    pub audio_sample_size: Option<f64>,
+    pub stereo: Option<bool>,
+    pub audio_codec_id: Option<MetadataCodecId>,
+    pub file_size: Option<f64>,
+    pub last_timestamp: Option<f64>,
+    pub last_keyframe_timestamp: Option<f64>,
+    pub encoder: Option<String>,
+    pub has_video: Option<bool>,
+    pub has_audio: Option<bool>,
+    pub has_metadata: Option<bool>,
+    pub has_keyframes: Option<bool>,
+    pub can_seek_to_end: Option<bool>,
+    pub keyframes: Option<KeyframeIndex>,
+
+    /// Props with other keys or a null value
+    pub extra: Vec<Amf0DataObjectProp>,
+
+    /// Keys in the order they came in, the props of keys that are not in it are written after
+    order: Vec<String>,
+
+    /// The props came in an `@setDataFrame` of `onMetaData`, it is written the same way
+    set_data_frame: bool,
+}
+
+/// `videocodecid`/`audiocodecid`: the codec id of the tags, newer encoders write a FourCC string
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetadataCodecId {
+    Id(u8),
+    FourCc(String),
+}
+
+impl MetadataCodecId {
+    fn to_value(&self) -> Result<Amf0Value, Amf0Error> {
+        match self {
+            MetadataCodecId::Id(id) => Ok(number_value(*id as f64)),
+            MetadataCodecId::FourCc(fourcc) => string_value(fourcc),
+        }
+    }
+}
+
+/// `keyframes`: Offset in the file and time of the keyframes, to seek without reading the tags
+#[derive(Debug, Clone, Default)]
+pub struct KeyframeIndex {
+    pub filepositions: Vec<u64>,
+    pub times: Vec<f64>,
+
+    /// Members other than `filepositions` and `times`
+    pub extra: Vec<Amf0DataObjectProp>,
+
+    /// Keys of the members in the order they came in, as in `OnMetaData`
+    order: Vec<String>,
+
+    /// The members came in an ECMA array instead of an object, it is written the same way
+    ecma_array: bool,
+}
+
+impl KeyframeIndex {
+    /// (time, file position) of every keyframe
+    pub fn iter(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.times
+            .iter()
+            .copied()
+            .zip(self.filepositions.iter().copied())
+    }
+
+    /// File position of the last keyframe at or before `time`, where to seek to play from it
+    pub fn seek_position(&self, time: f64) -> Option<u64> {
+        self.iter()
+            .take_while(|(keyframe, _)| *keyframe <= time)
+            .last()
+            .map(|(_, position)| position)
+    }
+
+    fn from_value(value: &Amf0Value) -> Result<Self, MetadataError> {
+        let (props, ecma_array) = match value {
+            Amf0Value::Object(object) => (&object.props, false),
+            Amf0Value::EcmaArray(array) => (&array.props, true),
+            _ => return Err(invalid_type("keyframes", "an object")),
+        };
+
+        let mut index = Self {
+            ecma_array,
+            ..Self::default()
+        };
+
+        for prop in props {
+            let key = prop.name.key.as_str();
+
+            index.order.push(key.to_string());
+
+            match key {
+                "filepositions" => {
+                    index.filepositions = numbers(key, &prop.value)?
+                        .into_iter()
+                        .map(|n| integer(key, n, u64::MAX as f64).map(|n| n as u64))
+                        .collect::<Result<_, _>>()?;
+                }
+                "times" => index.times = numbers(key, &prop.value)?,
+                _ => index.extra.push(prop.clone()),
+            }
+        }
+
+        if index.filepositions.len() != index.times.len() {
+            return Err(MetadataError::KeyframesMismatch {
+                filepositions: index.filepositions.len(),
+                times: index.times.len(),
+            });
+        }
+
+        Ok(index)
+    }
+
+    fn to_value(&self) -> Result<Amf0Value, Amf0Error> {
+        let filepositions = self
+            .filepositions
+            .iter()
+            .map(|position| number_value(*position as f64))
+            .collect();
+
+        let times = self.times.iter().map(|time| number_value(*time)).collect();
+
+        let known = vec![
+            prop(
+                "filepositions",
+                Amf0Value::StrictArray(Amf0StrictArray::new(filepositions)),
+            )?,
+            prop("times", Amf0Value::StrictArray(Amf0StrictArray::new(times)))?,
+        ];
+
+        let props = ordered_props(&self.order, known, &self.extra);
+
+        Ok(if self.ecma_array {
+            Amf0Value::EcmaArray(Amf0EcmaArray::new(props))
+        } else {
+            Amf0Value::Object(Amf0Object::new(props))
+        })
+    }
+}
+
+impl OnMetaData {
+    /// Fails if a standard key has a value of another type
+    pub fn from_props(props: &[Amf0DataObjectProp]) -> Result<Self, MetadataError> {
+        let mut metadata = Self::default();
+
+        for prop in props {
+            let key = prop.name.key.as_str();
+            let value = &prop.value;
+
+            metadata.order.push(key.to_string());
+
+            if matches!(value, Amf0Value::Null | Amf0Value::Undefined) {
+                metadata.extra.push(prop.clone());
+                continue;
+            }
+
+            match key {
+                "duration" => metadata.duration = Some(number(key, value)?),
+                "width" => metadata.width = Some(number(key, value)?),
+                "height" => metadata.height = Some(number(key, value)?),
+                "videodatarate" => metadata.video_data_rate = Some(number(key, value)?),
+                "framerate" => metadata.frame_rate = Some(number(key, value)?),
+                "videocodecid" => metadata.video_codec_id = Some(codec_id(key, value)?),
+                "audiodatarate" => metadata.audio_data_rate = Some(number(key, value)?),
+                "audiosamplerate" => metadata.audio_sample_rate = Some(number(key, value)?),
+                "audiosamplesize" => metadata.audio_sample_size = Some(number(key, value)?),
+                "stereo" => metadata.stereo = Some(boolean(key, value)?),
+                "audiocodecid" => metadata.audio_codec_id = Some(codec_id(key, value)?),
+                "filesize" => metadata.file_size = Some(number(key, value)?),
+                "lasttimestamp" => metadata.last_timestamp = Some(number(key, value)?),
+                "lastkeyframetimestamp" => {
+                    metadata.last_keyframe_timestamp = Some(number(key, value)?)
+                }
+                "encoder" => metadata.encoder = Some(string(key, value)?),
+                "hasVideo" => metadata.has_video = Some(boolean(key, value)?),
+                "hasAudio" => metadata.has_audio = Some(boolean(key, value)?),
+                "hasMetadata" => metadata.has_metadata = Some(boolean(key, value)?),
+                "hasKeyframes" => metadata.has_keyframes = Some(boolean(key, value)?),
+                "canSeekToEnd" => metadata.can_seek_to_end = Some(boolean(key, value)?),
+                "keyframes" => metadata.keyframes = Some(KeyframeIndex::from_value(value)?),
+                _ => metadata.extra.push(prop.clone()),
+            }
+        }
+
+        Ok(metadata)
+    }
+
+    /// The standard keys that are set and `extra` in the order the keys came in, then the rest of
+    /// the standard keys and of `extra`. A prop of `extra` with a standard key that is set is left out
+    pub fn to_props(&self) -> Result<Vec<Amf0DataObjectProp>, Amf0Error> {
+        let values = [
+            ("duration", self.duration.map(number_value)),
+            ("width", self.width.map(number_value)),
+            ("height", self.height.map(number_value)),
+            ("videodatarate", self.video_data_rate.map(number_value)),
+            ("framerate", self.frame_rate.map(number_value)),
+            (
+                "videocodecid",
+                self.video_codec_id
+                    .as_ref()
+                    .map(MetadataCodecId::to_value)
+                    .transpose()?,
+            ),
+            ("audiodatarate", self.audio_data_rate.map(number_value)),
+            ("audiosamplerate", self.audio_sample_rate.map(number_value)),
+            ("audiosamplesize", self.audio_sample_size.map(number_value)),
+            ("stereo", self.stereo.map(bool_value)),
+            (
+                "audiocodecid",
+                self.audio_codec_id
+                    .as_ref()
+                    .map(MetadataCodecId::to_value)
+                    .transpose()?,
+            ),
+            ("filesize", self.file_size.map(number_value)),
+            ("lasttimestamp", self.last_timestamp.map(number_value)),
+            (
+                "lastkeyframetimestamp",
+                self.last_keyframe_timestamp.map(number_value),
+            ),
+            (
+                "encoder",
+                self.encoder.as_deref().map(string_value).transpose()?,
+            ),
+            ("hasVideo", self.has_video.map(bool_value)),
+            ("hasAudio", self.has_audio.map(bool_value)),
+            ("hasMetadata", self.has_metadata.map(bool_value)),
+            ("hasKeyframes", self.has_keyframes.map(bool_value)),
+            ("canSeekToEnd", self.can_seek_to_end.map(bool_value)),
+            (
+                "keyframes",
+                self.keyframes
+                    .as_ref()
+                    .map(KeyframeIndex::to_value)
+                    .transpose()?,
+            ),
+        ];
+
+        let known = values
+            .into_iter()
+            .filter_map(|(key, value)| value.map(|value| prop(key, value)))
+            .collect::<Result<_, _>>()?;
+
+        Ok(ordered_props(&self.order, known, &self.extra))
+    }
+
+    /// Value of a key of `extra`, standard keys are read from their field
+    pub fn get_extra(&self, key: &str) -> Option<&Amf0Value> {
+        self.extra
+            .iter()
+            .find(|prop| prop.name.key == key)
+            .map(|prop| &prop.value)
+    }
+
+    /// Replaces the value of a key of `extra`, or appends it if there isn't one, standard keys
+    /// must be set with their field
+    pub fn set_extra(&mut self, key: &str, value: Amf0Value) -> Result<(), Amf0Error> {
+        match self.extra.iter_mut().find(|prop| prop.name.key == key) {
+            Some(prop) => prop.value = value,
+            None => self.extra.push(prop(key, value)?),
+        }
+
+        Ok(())
+    }
+
+    /// `videocodecid` as the codec of the video tags, `None` for a FourCC
+    pub fn video_codec(&self) -> Option<CodecId> {
+        match self.video_codec_id {
+            Some(MetadataCodecId::Id(id)) => Some(CodecId::from(id)),
+            _ => None,
+        }
+    }
+
+    /// `audiocodecid` as the sound format of the audio tags, `None` for a FourCC
+    pub fn sound_format(&self) -> Option<SoundFormat> {
+        match self.audio_codec_id {
+            Some(MetadataCodecId::Id(id)) => Some(SoundFormat::from(id)),
+            _ => None,
+        }
+    }
+}
+
+/// The script data must be `onMetaData` (or an `@setDataFrame` of it) with its props
+impl TryFrom<&FlvScriptTag> for OnMetaData {
+    type Error = MetadataError;
+
+    fn try_from(script: &FlvScriptTag) -> Result<Self, MetadataError> {
+        if !script.is_metadata() {
+            return Err(MetadataError::NotMetadata(script.data_name().to_string()));
+        }
+
+        let mut metadata = Self::from_props(script.props().ok_or(MetadataError::NoProps)?)?;
+
+        metadata.set_data_frame = script.name.content == FLV_SCRIPT_SET_DATA_FRAME;
+
+        Ok(metadata)
+    }
+}
+
+/// `onMetaData` with one ECMA array, wrapped in an `@setDataFrame` if it came in one
+impl TryFrom<&OnMetaData> for FlvScriptTag {
+    type Error = Amf0Error;
+
+    fn try_from(metadata: &OnMetaData) -> Result<Self, Amf0Error> {
+        let props = metadata.to_props()?;
+
+        if !metadata.set_data_frame {
+            return FlvScriptTag::from_props(FLV_SCRIPT_ON_METADATA.to_string(), props);
+        }
+
+        FlvScriptTag::new(
+            FLV_SCRIPT_SET_DATA_FRAME.to_string(),
+            vec![
+                string_value(FLV_SCRIPT_ON_METADATA)?,
+                Amf0Value::EcmaArray(Amf0EcmaArray::new(props)),
+            ],
+        )
+    }
+}
+
+/// `known` and `extra` in the order of the keys of `order`, then the props left of `known` and of
+/// `extra`. A prop of `extra` with the key of one of `known` is left out
+fn ordered_props(
+    order: &[String],
+    known: Vec<Amf0DataObjectProp>,
+    extra: &[Amf0DataObjectProp],
+) -> Vec<Amf0DataObjectProp> {
+    let mut extra: Vec<_> = extra
+        .iter()
+        .filter(|extra| !known.iter().any(|prop| prop.name.key == extra.name.key))
+        .map(Some)
+        .collect();
+    let mut known: Vec<_> = known.into_iter().map(Some).collect();
+
+    let mut props = Vec::with_capacity(known.len() + extra.len());
+
+    for key in order {
+        let is_key = |prop: &Amf0DataObjectProp| prop.name.key == *key;
+
+        let prop = known
+            .iter_mut()
+            .find(|prop| prop.as_ref().is_some_and(is_key))
+            .and_then(Option::take)
+            .or_else(|| {
+                extra
+                    .iter_mut()
+                    .find(|prop| prop.is_some_and(is_key))
+                    .and_then(Option::take)
+                    .cloned()
+            });
+
+        props.extend(prop);
+    }
+
+    props.extend(known.into_iter().flatten());
+    props.extend(extra.into_iter().flatten().cloned());
+
+    props
+}
+
+fn prop(key: &str, value: Amf0Value) -> Result<Amf0DataObjectProp, Amf0Error> {
+    Ok(Amf0DataObjectProp {
+        name: Amf0Key::new(key.to_string())?,
+        value,
+    })
+}
+
+fn number_value(n: f64) -> Amf0Value {
+    Amf0Value::Number(Amf0Number::new(n))
+}
+
+fn bool_value(b: bool) -> Amf0Value {
+    Amf0Value::Bool(Amf0Bool::new(b))
+}
+
+/// A long string if `s` doesn't fit in a string
+fn string_value(s: &str) -> Result<Amf0Value, Amf0Error> {
+    if s.len() > u16::MAX as usize {
+        return Ok(Amf0Value::LongString(Amf0LongString::new(s.to_string())?));
+    }
+
+    Ok(Amf0Value::String(Amf0String::new(s.to_string())?))
+}
+
+fn invalid_type(key: &str, expected: &'static str) -> MetadataError {
+    MetadataError::InvalidType {
+        key: key.to_string(),
+        expected,
+    }
+}
+
+fn number(key: &str, value: &Amf0Value) -> Result<f64, MetadataError> {
+    match value {
+        Amf0Value::Number(n) => Ok(n.value()),
+        _ => Err(invalid_type(key, "a number")),
+    }
+}
+
+/// A number without a fractional part from 0 to `max`
+fn integer(key: &str, n: f64, max: f64) -> Result<f64, MetadataError> {
+    if n.fract() != 0.0 || !(0.0..=max).contains(&n) {
+        return Err(invalid_type(key, "an integer"));
+    }
+
+    Ok(n)
+}
+
+fn boolean(key: &str, value: &Amf0Value) -> Result<bool, MetadataError> {
+    match value {
+        Amf0Value::Bool(b) => Ok(b.value()),
+        _ => Err(invalid_type(key, "a boolean")),
+    }
+}
+
+fn string(key: &str, value: &Amf0Value) -> Result<String, MetadataError> {
+    match value {
+        Amf0Value::String(s) => Ok(s.content.clone()),
+        Amf0Value::LongString(s) => Ok(s.content.clone()),
+        _ => Err(invalid_type(key, "a string")),
+    }
+}
+
+fn codec_id(key: &str, value: &Amf0Value) -> Result<MetadataCodecId, MetadataError> {
+    match value {
+        Amf0Value::Number(n) => {
+            let id = integer(key, n.value(), u8::MAX as f64)?;
+
+            Ok(MetadataCodecId::Id(id as u8))
+        }
+        Amf0Value::String(fourcc) => Ok(MetadataCodecId::FourCc(fourcc.content.clone())),
+        _ => Err(invalid_type(key, "a codec id or a FourCC")),
+    }
+}
+
+/// Numbers of a strict array
+fn numbers(key: &str, value: &Amf0Value) -> Result<Vec<f64>, MetadataError> {
+    let Amf0Value::StrictArray(array) = value else {
+        return Err(invalid_type(key, "an array of numbers"));
+    };
+
+    array
+        .values
+        .iter()
+        .map(|value| match value {
+            Amf0Value::Number(n) => Ok(n.value()),
+            _ => Err(invalid_type(key, "an array of numbers")),
+        })
+        .collect()
+}
+
+#[derive(Error, Debug)]
+pub enum MetadataError {
+    /// Name of the script data that is not `onMetaData`
+    #[error("Not onMetaData: {0}")]
+    NotMetadata(String),
+
+    /// The script data has no ECMA array or object
+    #[error("No props")]
+    NoProps,
+
+    #[error("Invalid type of {key}: expected {expected}")]
+    InvalidType { key: String, expected: &'static str },
+
+    #[error("Keyframes: {filepositions} file positions, {times} times")]
+    KeyframesMismatch { filepositions: usize, times: usize },
+}
@@ -1,5 +1,102 @@
+pub mod amf3;
 pub mod audio;
+pub mod encryption;
 pub mod header;
+pub mod limits;
+pub mod metadata;
 pub mod script;
 pub mod tag;
+pub mod timestamp;
 pub mod video;
+
+use std::io::Read;
+
+use crate::error::FlvError;
+
+/// Audio/video payload, with the `bytes` feature it is a reference-counted `bytes::Bytes` so
+/// cloning a tag doesn't copy its payload
+#[cfg(not(feature = "bytes"))]
+pub type Payload = Vec<u8>;
+
+/// Audio/video payload, with the `bytes` feature it is a reference-counted `bytes::Bytes` so
+/// cloning a tag doesn't copy its payload
+#[cfg(feature = "bytes")]
+pub type Payload = bytes::Bytes;
+
+#[cfg(not(feature = "bytes"))]
+#[inline]
+pub(crate) fn payload(data: Vec<u8>) -> Payload {
+    data
+}
+
+#[cfg(feature = "bytes")]
+#[inline]
+pub(crate) fn payload(data: Vec<u8>) -> Payload {
+    Payload::from(data)
+}
+
+#[cfg(not(feature = "bytes"))]
+#[inline]
+pub(crate) fn advance_payload(data: &mut Payload, n: usize) {
+    data.drain(..n);
+}
+
+#[cfg(feature = "bytes")]
+#[inline]
+pub(crate) fn advance_payload(data: &mut Payload, n: usize) {
+    bytes::Buf::advance(data, n);
+}
+
+#[inline]
+pub(crate) fn payload_from_slice(data: &[u8]) -> Payload {
+    payload(data.to_vec())
+}
+
+/// Splits the first `n` bytes of `input`, used by the borrowed parsers
+pub(crate) fn take<'a>(input: &mut &'a [u8], n: usize) -> Result<&'a [u8], FlvError> {
+    let (bytes, rest) = input.split_at_checked(n).ok_or(FlvError::Truncated {
+        expected: n,
+        got: input.len(),
+    })?;
+
+    *input = rest;
+
+    Ok(bytes)
+}
+
+/// Size of the payload that follows `header` bytes of headers in `data_size` bytes of tag data
+pub(crate) fn payload_size(data_size: usize, header: usize) -> Result<usize, FlvError> {
+    data_size
+        .checked_sub(header)
+        .ok_or(FlvError::DataSizeTooSmall(data_size))
+}
+
+/// Reads exactly `size` bytes of payload, a stream that ends before is `Truncated`
+pub(crate) fn read_payload<R: Read>(stream: &mut R, size: usize) -> Result<Payload, FlvError> {
+    // `size` is at most a U24, so the capacity is bounded even for a bogus size
+    let mut data = Vec::with_capacity(size.min(tag::FLV_TAG_MAX_DATA_SIZE as usize));
+
+    let got = stream.by_ref().take(size as u64).read_to_end(&mut data)?;
+
+    if got < size {
+        return Err(FlvError::Truncated {
+            expected: size,
+            got,
+        });
+    }
+
+    Ok(payload(data))
+}
+
+/// Reads a big endian `u32`, a stream that ends before is `Truncated`
+pub(crate) fn read_u32_be<R: Read>(stream: &mut R) -> Result<u32, FlvError> {
+    let mut buf = Vec::with_capacity(4);
+
+    let got = stream.by_ref().take(4).read_to_end(&mut buf)?;
+
+    let buf: [u8; 4] = buf
+        .try_into()
+        .map_err(|_| FlvError::Truncated { expected: 4, got })?;
+
+    Ok(u32::from_be_bytes(buf))
+}
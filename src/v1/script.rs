@@ -1,155 +1,376 @@
-use std::{str::Utf8Error, string::FromUtf8Error};
+use std::{io::Read, string::FromUtf8Error};
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use thiserror::Error;
 
-#[derive(Debug)]
+use crate::v1::{
+    Payload,
+    amf3::{Amf3Error, Amf3Value},
+    limits::{DecodeBudget, DecodeLimits},
+    payload_from_slice,
+    tag::FLV_TAG_MAX_DATA_SIZE,
+};
+
+/// Name of the script data with the metadata of the file, the first tag of the file
+pub const FLV_SCRIPT_ON_METADATA: &str = "onMetaData";
+
+/// Name of the script data that wraps another one, its first value is the name of the wrapped
+/// data (`@setDataFrame`, `onMetaData`, props)
+pub const FLV_SCRIPT_SET_DATA_FRAME: &str = "@setDataFrame";
+
+/// FlvScriptTag: A name followed by any number of AMF0 values, `onMetaData` has one ECMA array,
+/// `onCuePoint` an object, `|RtmpSampleAccess` two booleans
+#[derive(Debug, Clone)]
 pub struct FlvScriptTag {
     pub name: Amf0String,
-    pub ecma: Amf0EcmaArray,
+    pub values: Vec<Amf0Value>,
 
-    pub data_size: usize,
+    /// Junk some encoders write after the last value, a stray object end and/or zero padding, kept
+    /// to write it again
+    pub trailing: Payload,
 }
 
 impl FlvScriptTag {
-    pub fn new(name: String, props: Vec<Amf0DataObjectProp>) -> Result<Self, Amf0Error> {
-        let amf0_name = Amf0String::new(name)?;
-        let ecma = Amf0EcmaArray::new(props);
+    pub fn new(name: String, values: Vec<Amf0Value>) -> Result<Self, Amf0Error> {
+        Ok(Self {
+            name: Amf0String::new(name)?,
+            values,
+            trailing: Payload::new(),
+        })
+    }
+
+    /// One ECMA array with `props`, as `onMetaData`
+    pub fn from_props(name: String, props: Vec<Amf0DataObjectProp>) -> Result<Self, Amf0Error> {
+        Self::new(name, vec![Amf0Value::EcmaArray(Amf0EcmaArray::new(props))])
+    }
 
-        let data_size = amf0_name.size() + ecma.size();
+    pub fn encoded_len(&self) -> usize {
+        let values = self
+            .values
+            .iter()
+            .map(Amf0Value::encoded_len)
+            .sum::<usize>();
 
-        Ok(Self {
-            name: amf0_name,
-            ecma,
-            data_size,
+        self.name.encoded_len() + values + self.trailing.len()
+    }
+
+    /// Name of the data, for `@setDataFrame` the name of the data it wraps
+    pub fn data_name(&self) -> &str {
+        match self.values.first() {
+            Some(Amf0Value::String(name)) if self.name.content == FLV_SCRIPT_SET_DATA_FRAME => {
+                &name.content
+            }
+            _ => &self.name.content,
+        }
+    }
+
+    pub fn is_metadata(&self) -> bool {
+        self.data_name() == FLV_SCRIPT_ON_METADATA
+    }
+
+    /// Props of the first ECMA array or object of the values
+    pub fn props(&self) -> Option<&[Amf0DataObjectProp]> {
+        self.values.iter().find_map(|value| match value {
+            Amf0Value::EcmaArray(array) => Some(array.props.as_slice()),
+            Amf0Value::Object(object) => Some(object.props.as_slice()),
+            _ => None,
         })
     }
 
-    pub const fn size(&self) -> usize {
-        self.data_size
+    pub fn props_mut(&mut self) -> Option<&mut Vec<Amf0DataObjectProp>> {
+        self.values.iter_mut().find_map(|value| match value {
+            Amf0Value::EcmaArray(array) => Some(&mut array.props),
+            Amf0Value::Object(object) => Some(&mut object.props),
+            _ => None,
+        })
     }
 
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
         self.name.encode(stream)?;
-        self.ecma.encode(stream)?;
+
+        for value in &self.values {
+            value.encode(stream)?;
+        }
+
+        stream.write_all(&self.trailing)?;
 
         Ok(())
     }
     pub fn decode<T: ReadBytesExt>(stream: &mut T, data_size: usize) -> Result<Self, Amf0Error> {
-        let name = Amf0String::decode(stream)?;
-        let ecma = Amf0EcmaArray::decode(stream)?;
+        Self::decode_with(stream, data_size, &mut DecodeBudget::default())
+    }
+    pub fn decode_with_limits<T: ReadBytesExt>(
+        stream: &mut T,
+        data_size: usize,
+        limits: &DecodeLimits,
+    ) -> Result<Self, Amf0Error> {
+        Self::decode_with(stream, data_size, &mut DecodeBudget::new(*limits))
+    }
+    /// Exactly `data_size` bytes are read from `stream`, values are decoded until the end of the
+    /// data or a stray object end or zero padding, which is kept as `trailing`. Any other bytes must
+    /// decode as values
+    pub(crate) fn decode_with<T: ReadBytesExt>(
+        stream: &mut T,
+        data_size: usize,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
+        budget.alloc(data_size)?;
+
+        // the data is read first, so the trailing bytes after the values can be kept
+        let mut data = Vec::with_capacity(data_size.min(FLV_TAG_MAX_DATA_SIZE as usize));
+
+        let got = stream
+            .by_ref()
+            .take(data_size as u64)
+            .read_to_end(&mut data)?;
+
+        if got < data_size {
+            return Err(Amf0Error::Truncated {
+                expected: data_size,
+                got,
+            });
+        }
+
+        let mut input = data.as_slice();
+
+        let name = Amf0String::decode_with(&mut input, budget)?;
+
+        let mut values = Vec::new();
+
+        while !is_known_trailing(input) {
+            budget.element::<Amf0Value>(values.len())?;
+
+            values.push(Amf0Value::decode_with(&mut input, budget)?);
+        }
 
         Ok(Self {
             name,
-            ecma,
-            data_size,
+            values,
+            trailing: payload_from_slice(input),
         })
     }
 }
 
 // AMF0
 
-// NOTE FOR CODE READERS: `extract` is a function that don't expect an ID
+// NOTE FOR CODE READERS: `extract` is a function that don't expect an ID, the `_with` variants
+// account what they allocate in a `DecodeBudget`
 
 const AMF0_STRING: u8 = 2;
 const AMF0_NUMBER: u8 = 0;
 const AMF0_BOOL: u8 = 1;
+const AMF0_OBJECT: u8 = 3;
 const AMF0_ECMA_ARRAY: u8 = 8;
+const AMF0_OBJECT_END: u8 = 9;
+const AMF0_STRICT_ARRAY: u8 = 10;
+const AMF0_NULL: u8 = 5;
+const AMF0_UNDEFINED: u8 = 6;
+const AMF0_REFERENCE: u8 = 7;
+const AMF0_DATE: u8 = 11;
+const AMF0_LONG_STRING: u8 = 12;
+const AMF0_UNSUPPORTED: u8 = 13;
+const AMF0_XML_DOCUMENT: u8 = 15;
+const AMF0_TYPED_OBJECT: u8 = 16;
+const AMF0_AVMPLUS: u8 = 17;
+
+/// Object end with no object open, written by some encoders after the values of a script tag
+const AMF0_STRAY_OBJECT_END: &[u8] = &[0, 0, AMF0_OBJECT_END];
+
+/// `input` is junk some encoders write after the values: a stray object end followed by zero
+/// padding, or zero padding too short to be a number
+fn is_known_trailing(input: &[u8]) -> bool {
+    let is_padding = |bytes: &[u8]| bytes.iter().all(|&b| b == 0);
+
+    match input.strip_prefix(AMF0_STRAY_OBJECT_END) {
+        Some(padding) => is_padding(padding),
+        // marker + f64
+        None => input.len() < 1 + 8 && is_padding(input),
+    }
+}
+
+/// Reads the `size` bytes of a string, a stream that ends before is `Truncated`
+fn read_utf8<T: ReadBytesExt>(stream: &mut T, size: usize) -> Result<String, Amf0Error> {
+    let mut content = Vec::with_capacity(size);
+
+    let got = stream
+        .by_ref()
+        .take(size as u64)
+        .read_to_end(&mut content)?;
+
+    if got < size {
+        return Err(Amf0Error::Truncated {
+            expected: size,
+            got,
+        });
+    }
 
-#[derive(Debug)]
+    Ok(String::from_utf8(content)?)
+}
+
+/// Length of an AMF0 string as its U16 prefix, it can't be longer
+fn string_len(content: &str) -> Result<u16, Amf0Error> {
+    u16::try_from(content.len()).map_err(|_| Amf0Error::StringTooLong)
+}
+
+/// Length of an AMF0 long string or XML document as its U32 prefix, it can't be longer
+fn long_string_len(content: &str) -> Result<u32, Amf0Error> {
+    u32::try_from(content.len()).map_err(|_| Amf0Error::StringTooLong)
+}
+
+/// Props of an object, ECMA array or typed object + the object end
+fn props_len(props: &[Amf0DataObjectProp]) -> usize {
+    props
+        .iter()
+        .map(Amf0DataObjectProp::encoded_len)
+        .sum::<usize>()
+        + 3
+}
+
+/// Writes `props` followed by the object end, an empty key and the object end marker
+fn encode_props<T: WriteBytesExt>(
+    stream: &mut T,
+    props: &[Amf0DataObjectProp],
+) -> Result<(), Amf0Error> {
+    for prop in props {
+        prop.encode(stream)?;
+    }
+
+    stream.write_u16::<BigEndian>(0)?;
+    stream.write_u8(AMF0_OBJECT_END)?;
+
+    Ok(())
+}
+
+/// Reads props until the object end, an empty key followed by the object end marker
+fn decode_props<T: ReadBytesExt>(
+    stream: &mut T,
+    budget: &mut DecodeBudget,
+) -> Result<Vec<Amf0DataObjectProp>, Amf0Error> {
+    budget.enter()?;
+
+    let mut props = Vec::new();
+
+    loop {
+        let name = Amf0Key::decode_with(stream, budget)?;
+
+        if name.key.is_empty() {
+            if stream.read_u8()? != AMF0_OBJECT_END {
+                return Err(Amf0Error::InvalidId);
+            }
+
+            break;
+        }
+
+        budget.element::<Amf0DataObjectProp>(props.len())?;
+
+        let value = Amf0Value::decode_with(stream, budget)?;
+
+        props.push(Amf0DataObjectProp { name, value });
+    }
+
+    budget.exit();
+
+    Ok(props)
+}
+
+#[derive(Debug, Clone)]
 pub struct Amf0Key {
-    pub size: u16,
     pub key: String,
 }
 
 impl Amf0Key {
-    pub const fn size(&self) -> usize {
+    pub const fn encoded_len(&self) -> usize {
         2 + self.key.len()
     }
     pub fn new(key: String) -> Result<Self, Amf0Error> {
-        if key.len() > (u16::MAX) as usize {
-            return Err(Amf0Error::StringTooLong);
-        }
+        string_len(&key)?;
 
-        let size = key.len() as u16;
-
-        Ok(Self { size, key })
+        Ok(Self { key })
     }
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
-        stream.write_u16::<BigEndian>(self.size)?;
-        stream.write(self.key.as_bytes())?;
+        stream.write_u16::<BigEndian>(string_len(&self.key)?)?;
+        stream.write_all(self.key.as_bytes())?;
 
         Ok(())
     }
     pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::decode_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn decode_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
         let size = stream.read_u16::<BigEndian>()?;
 
-        let mut key = vec![0_u8; size as usize];
-
-        stream.read(&mut key)?;
+        budget.string(size as usize)?;
 
-        let key = String::from_utf8(key)?;
+        let key = read_utf8(stream, size as usize)?;
 
-        Ok(Self { size, key })
+        Ok(Self { key })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Amf0String {
-    pub size: u16,
     pub content: String,
 }
 
 impl Amf0String {
-    pub const fn size(&self) -> usize {
+    pub const fn encoded_len(&self) -> usize {
         1 + 2 + self.content.len()
     }
 
     pub fn new(content: String) -> Result<Self, Amf0Error> {
-        if content.len() > (u16::MAX) as usize {
-            return Err(Amf0Error::StringTooLong);
-        }
-
-        let size = content.len() as u16;
+        string_len(&content)?;
 
-        Ok(Self { size, content })
+        Ok(Self { content })
     }
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
         stream.write_u8(AMF0_STRING)?;
-        stream.write_u16::<BigEndian>(self.size)?;
-        stream.write(self.content.as_bytes())?;
+        stream.write_u16::<BigEndian>(string_len(&self.content)?)?;
+        stream.write_all(self.content.as_bytes())?;
 
         Ok(())
     }
 
     pub fn extract<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::extract_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn extract_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
         let size = stream.read_u16::<BigEndian>()?;
 
-        let mut content = vec![0_u8; size as usize];
-
-        stream.read(&mut content)?;
+        budget.string(size as usize)?;
 
-        let content = String::from_utf8(content)?;
+        let content = read_utf8(stream, size as usize)?;
 
-        Ok(Self { content, size })
+        Ok(Self { content })
     }
 
     pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::decode_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn decode_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
         let ty = stream.read_u8()?;
 
         if ty != AMF0_STRING {
             return Err(Amf0Error::InvalidId);
         }
 
-        Self::extract(stream)
+        Self::extract_with(stream, budget)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Amf0Bool(bool);
 
 impl Amf0Bool {
-    pub const fn size(&self) -> usize {
+    pub const fn encoded_len(&self) -> usize {
         2
     }
 
@@ -157,6 +378,10 @@ impl Amf0Bool {
         Self(val)
     }
 
+    pub const fn value(&self) -> bool {
+        self.0
+    }
+
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
         stream.write_u8(AMF0_BOOL)?;
         stream.write_u8(self.0 as u8)?;
@@ -176,15 +401,15 @@ impl Amf0Bool {
             return Err(Amf0Error::InvalidId);
         }
 
-        Ok(Self::extract(stream)?)
+        Self::extract(stream)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Amf0Number(f64);
 
 impl Amf0Number {
-    pub const fn size(&self) -> usize {
+    pub const fn encoded_len(&self) -> usize {
         1 + 8
     }
 
@@ -192,6 +417,10 @@ impl Amf0Number {
         Self(val)
     }
 
+    pub const fn value(&self) -> f64 {
+        self.0
+    }
+
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
         stream.write_u8(AMF0_NUMBER)?;
         stream.write_f64::<BigEndian>(self.0)?;
@@ -210,79 +439,427 @@ impl Amf0Number {
             return Err(Amf0Error::InvalidId);
         }
 
-        Ok(Self::extract(stream)?)
+        Self::extract(stream)
     }
 }
 
-#[derive(Debug)]
-pub struct Amf0EcmaArray {
-    pub len: u32,
-    pub props: Vec<Amf0DataObjectProp>, // LIST TERMINATOR U8[3] = 0, 0, 9
+/// Index of an object, ECMA array, strict array or typed object decoded before it in the same
+/// script data, kept as is without resolving it
+#[derive(Debug, Clone)]
+pub struct Amf0Reference(u16);
+
+impl Amf0Reference {
+    pub const fn encoded_len(&self) -> usize {
+        1 + 2
+    }
+
+    pub fn new(index: u16) -> Self {
+        Self(index)
+    }
+
+    pub const fn index(&self) -> u16 {
+        self.0
+    }
+
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
+        stream.write_u8(AMF0_REFERENCE)?;
+        stream.write_u16::<BigEndian>(self.0)?;
+        Ok(())
+    }
+
+    pub fn extract<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        let index = stream.read_u16::<BigEndian>()?;
+        Ok(Self(index))
+    }
+
+    pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        let ty = stream.read_u8()?;
+
+        if ty != AMF0_REFERENCE {
+            return Err(Amf0Error::InvalidId);
+        }
+
+        Self::extract(stream)
+    }
 }
 
-impl Amf0EcmaArray {
-    pub fn size(&self) -> usize {
-        let base = 1 + 4 + 3;
+#[derive(Debug, Clone)]
+pub struct Amf0Date {
+    /// Milliseconds since the Unix epoch, UTC
+    pub millis: f64,
 
-        let size = self.props.iter().fold(0, |mut acc, v| {
-            acc += v.size();
-            acc
-        });
+    /// S16; reserved, should be 0
+    pub timezone: i16,
+}
 
-        size + base
+impl Amf0Date {
+    pub const fn encoded_len(&self) -> usize {
+        1 + 8 + 2
     }
-    pub fn new(props: Vec<Amf0DataObjectProp>) -> Self {
+
+    pub fn new(millis: f64) -> Self {
         Self {
-            len: props.len() as u32,
-            props: props,
+            millis,
+            timezone: 0,
         }
     }
+
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
-        stream.write_u8(AMF0_ECMA_ARRAY)?;
+        stream.write_u8(AMF0_DATE)?;
+        stream.write_f64::<BigEndian>(self.millis)?;
+        stream.write_i16::<BigEndian>(self.timezone)?;
+        Ok(())
+    }
+
+    pub fn extract<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        let millis = stream.read_f64::<BigEndian>()?;
+        let timezone = stream.read_i16::<BigEndian>()?;
+
+        Ok(Self { millis, timezone })
+    }
+
+    pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        let ty = stream.read_u8()?;
+
+        if ty != AMF0_DATE {
+            return Err(Amf0Error::InvalidId);
+        }
+
+        Self::extract(stream)
+    }
+}
+
+/// String with a U32 length, for strings longer than 65535 bytes
+#[derive(Debug, Clone)]
+pub struct Amf0LongString {
+    pub content: String,
+}
+
+impl Amf0LongString {
+    pub const fn encoded_len(&self) -> usize {
+        1 + 4 + self.content.len()
+    }
+
+    pub fn new(content: String) -> Result<Self, Amf0Error> {
+        long_string_len(&content)?;
+
+        Ok(Self { content })
+    }
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
+        stream.write_u8(AMF0_LONG_STRING)?;
+        stream.write_u32::<BigEndian>(long_string_len(&self.content)?)?;
+        stream.write_all(self.content.as_bytes())?;
+
+        Ok(())
+    }
+
+    pub fn extract<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::extract_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn extract_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
+        let size = stream.read_u32::<BigEndian>()?;
+
+        budget.string(size as usize)?;
+
+        let content = read_utf8(stream, size as usize)?;
 
-        stream.write_u32::<BigEndian>(self.len)?;
+        Ok(Self { content })
+    }
 
-        for prop in &self.props {
-            prop.encode(stream)?;
+    pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        let ty = stream.read_u8()?;
+
+        if ty != AMF0_LONG_STRING {
+            return Err(Amf0Error::InvalidId);
         }
 
-        stream.write_u24::<BigEndian>(0x000009)?;
+        Self::extract(stream)
+    }
+}
+
+/// XML document as a string with a U32 length
+#[derive(Debug, Clone)]
+pub struct Amf0XmlDocument {
+    pub content: String,
+}
+
+impl Amf0XmlDocument {
+    pub const fn encoded_len(&self) -> usize {
+        1 + 4 + self.content.len()
+    }
+
+    pub fn new(content: String) -> Result<Self, Amf0Error> {
+        long_string_len(&content)?;
+
+        Ok(Self { content })
+    }
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
+        stream.write_u8(AMF0_XML_DOCUMENT)?;
+        stream.write_u32::<BigEndian>(long_string_len(&self.content)?)?;
+        stream.write_all(self.content.as_bytes())?;
 
         Ok(())
     }
+
+    pub fn extract<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::extract_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn extract_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
+        let size = stream.read_u32::<BigEndian>()?;
+
+        budget.string(size as usize)?;
+
+        let content = read_utf8(stream, size as usize)?;
+
+        Ok(Self { content })
+    }
+
+    pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        let ty = stream.read_u8()?;
+
+        if ty != AMF0_XML_DOCUMENT {
+            return Err(Amf0Error::InvalidId);
+        }
+
+        Self::extract(stream)
+    }
+}
+
+/// Associative array, the object of the script data of a tag
+#[derive(Debug, Clone)]
+pub struct Amf0EcmaArray {
+    pub props: Vec<Amf0DataObjectProp>, // OBJECT END U8[3] = 0, 0, 9
+
+    /// U32 count sent before the props and the number of props decoded, the count is only a hint
+    /// (encoders often get it wrong) and is written back as long as the props are not added or
+    /// removed
+    decoded: Option<(u32, usize)>,
+}
+
+impl Amf0EcmaArray {
+    pub fn encoded_len(&self) -> usize {
+        1 + 4 + props_len(&self.props)
+    }
+    pub fn new(props: Vec<Amf0DataObjectProp>) -> Self {
+        Self {
+            props,
+            decoded: None,
+        }
+    }
+    /// U32 count written before the props, the decoded one unless props were added or removed
+    pub fn count(&self) -> u32 {
+        match self.decoded {
+            Some((count, len)) if len == self.props.len() => count,
+            _ => self.props.len() as u32,
+        }
+    }
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
+        stream.write_u8(AMF0_ECMA_ARRAY)?;
+
+        stream.write_u32::<BigEndian>(self.count())?;
+
+        encode_props(stream, &self.props)
+    }
+    pub fn extract<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::extract_with(stream, &mut DecodeBudget::default())
+    }
+    /// The props are read until the object end, whatever `count` says
+    pub(crate) fn extract_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
+        let count = stream.read_u32::<BigEndian>()?;
+
+        let props = decode_props(stream, budget)?;
+
+        Ok(Self {
+            decoded: Some((count, props.len())),
+            props,
+        })
+    }
     pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::decode_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn decode_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
         let ty = stream.read_u8()?;
 
         if ty != AMF0_ECMA_ARRAY {
             return Err(Amf0Error::InvalidId);
         }
 
+        Self::extract_with(stream, budget)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Amf0Object {
+    pub props: Vec<Amf0DataObjectProp>, // OBJECT END U8[3] = 0, 0, 9
+}
+
+impl Amf0Object {
+    pub fn encoded_len(&self) -> usize {
+        1 + props_len(&self.props)
+    }
+    pub fn new(props: Vec<Amf0DataObjectProp>) -> Self {
+        Self { props }
+    }
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
+        stream.write_u8(AMF0_OBJECT)?;
+
+        encode_props(stream, &self.props)
+    }
+    pub fn extract<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::extract_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn extract_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
+        let props = decode_props(stream, budget)?;
+
+        Ok(Self { props })
+    }
+    pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        let ty = stream.read_u8()?;
+
+        if ty != AMF0_OBJECT {
+            return Err(Amf0Error::InvalidId);
+        }
+
+        Self::extract(stream)
+    }
+}
+
+/// Object with the name of its class
+#[derive(Debug, Clone)]
+pub struct Amf0TypedObject {
+    pub class_name: Amf0Key,
+    pub props: Vec<Amf0DataObjectProp>, // OBJECT END U8[3] = 0, 0, 9
+}
+
+impl Amf0TypedObject {
+    pub fn encoded_len(&self) -> usize {
+        1 + self.class_name.encoded_len() + props_len(&self.props)
+    }
+    pub fn new(class_name: String, props: Vec<Amf0DataObjectProp>) -> Result<Self, Amf0Error> {
+        Ok(Self {
+            class_name: Amf0Key::new(class_name)?,
+            props,
+        })
+    }
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
+        stream.write_u8(AMF0_TYPED_OBJECT)?;
+
+        self.class_name.encode(stream)?;
+
+        encode_props(stream, &self.props)
+    }
+    pub fn extract<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::extract_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn extract_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
+        let class_name = Amf0Key::decode_with(stream, budget)?;
+        let props = decode_props(stream, budget)?;
+
+        Ok(Self { class_name, props })
+    }
+    pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        let ty = stream.read_u8()?;
+
+        if ty != AMF0_TYPED_OBJECT {
+            return Err(Amf0Error::InvalidId);
+        }
+
+        Self::extract(stream)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Amf0StrictArray {
+    pub values: Vec<Amf0Value>,
+}
+
+impl Amf0StrictArray {
+    pub fn encoded_len(&self) -> usize {
+        let base = 1 + 4;
+
+        let size = self.values.iter().fold(0, |mut acc, v| {
+            acc += v.encoded_len();
+            acc
+        });
+
+        size + base
+    }
+    pub fn new(values: Vec<Amf0Value>) -> Self {
+        Self { values }
+    }
+    pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
+        stream.write_u8(AMF0_STRICT_ARRAY)?;
+
+        stream.write_u32::<BigEndian>(self.values.len() as u32)?;
+
+        for value in &self.values {
+            value.encode(stream)?;
+        }
+
+        Ok(())
+    }
+    pub fn extract<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::extract_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn extract_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
         let len = stream.read_u32::<BigEndian>()?;
 
-        let mut props = Vec::new();
+        budget.array(len as usize)?;
+        budget.enter()?;
+
+        // `len` comes from the input, so nothing is reserved with it
+        let mut values = Vec::new();
 
-        for _ in 0..len {
-            props.push(Amf0DataObjectProp::decode(stream)?);
+        for index in 0..len as usize {
+            budget.element::<Amf0Value>(index)?;
+            values.push(Amf0Value::decode_with(stream, budget)?);
         }
 
-        // read list terminator
-        let a = stream.read_u24::<BigEndian>()?;
+        budget.exit();
 
-        println!("{:?}", a);
+        Ok(Self { values })
+    }
+    pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        let ty = stream.read_u8()?;
 
-        Ok(Self { len, props })
+        if ty != AMF0_STRICT_ARRAY {
+            return Err(Amf0Error::InvalidId);
+        }
+
+        Self::extract(stream)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Amf0DataObjectProp {
     pub name: Amf0Key,
     pub value: Amf0Value,
 }
 
 impl Amf0DataObjectProp {
-    pub const fn size(&self) -> usize {
-        self.name.size() + self.value.size()
+    pub fn encoded_len(&self) -> usize {
+        self.name.encoded_len() + self.value.encoded_len()
     }
 
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
@@ -292,26 +869,56 @@ impl Amf0DataObjectProp {
         Ok(())
     }
     pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
-        let name = Amf0Key::decode(stream)?;
-        let value = Amf0Value::decode(stream)?;
+        Self::decode_with(stream, &mut DecodeBudget::default())
+    }
+    pub(crate) fn decode_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
+        let name = Amf0Key::decode_with(stream, budget)?;
+        let value = Amf0Value::decode_with(stream, budget)?;
 
         Ok(Self { name, value })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Amf0Value {
     String(Amf0String),
     Bool(Amf0Bool),
     Number(Amf0Number),
+    Object(Amf0Object),
+    StrictArray(Amf0StrictArray),
+    Null,
+    Undefined,
+    Reference(Amf0Reference),
+    EcmaArray(Amf0EcmaArray),
+    Date(Amf0Date),
+    LongString(Amf0LongString),
+    /// Marker of a type that can't be serialized
+    Unsupported,
+    XmlDocument(Amf0XmlDocument),
+    TypedObject(Amf0TypedObject),
+    /// AMF3 value after the `avmplus-object` marker, with its own reference tables
+    Amf3(Amf3Value),
 }
 
 impl Amf0Value {
-    pub const fn size(&self) -> usize {
+    pub fn encoded_len(&self) -> usize {
         match self {
-            Amf0Value::String(amf) => amf.size(),
-            Amf0Value::Bool(amf) => amf.size(),
-            Amf0Value::Number(amf) => amf.size(),
+            Amf0Value::String(amf) => amf.encoded_len(),
+            Amf0Value::Bool(amf) => amf.encoded_len(),
+            Amf0Value::Number(amf) => amf.encoded_len(),
+            Amf0Value::Object(amf) => amf.encoded_len(),
+            Amf0Value::StrictArray(amf) => amf.encoded_len(),
+            Amf0Value::Null | Amf0Value::Undefined | Amf0Value::Unsupported => 1,
+            Amf0Value::Reference(amf) => amf.encoded_len(),
+            Amf0Value::EcmaArray(amf) => amf.encoded_len(),
+            Amf0Value::Date(amf) => amf.encoded_len(),
+            Amf0Value::LongString(amf) => amf.encoded_len(),
+            Amf0Value::XmlDocument(amf) => amf.encoded_len(),
+            Amf0Value::TypedObject(amf) => amf.encoded_len(),
+            Amf0Value::Amf3(amf) => 1 + amf.encoded_len(),
         }
     }
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), Amf0Error> {
@@ -319,15 +926,59 @@ impl Amf0Value {
             Amf0Value::String(amf) => amf.encode(stream),
             Amf0Value::Bool(amf) => amf.encode(stream),
             Amf0Value::Number(amf) => amf.encode(stream),
+            Amf0Value::Object(amf) => amf.encode(stream),
+            Amf0Value::StrictArray(amf) => amf.encode(stream),
+            Amf0Value::Null => Ok(stream.write_u8(AMF0_NULL)?),
+            Amf0Value::Undefined => Ok(stream.write_u8(AMF0_UNDEFINED)?),
+            Amf0Value::Unsupported => Ok(stream.write_u8(AMF0_UNSUPPORTED)?),
+            Amf0Value::Reference(amf) => amf.encode(stream),
+            Amf0Value::EcmaArray(amf) => amf.encode(stream),
+            Amf0Value::Date(amf) => amf.encode(stream),
+            Amf0Value::LongString(amf) => amf.encode(stream),
+            Amf0Value::XmlDocument(amf) => amf.encode(stream),
+            Amf0Value::TypedObject(amf) => amf.encode(stream),
+            Amf0Value::Amf3(amf) => {
+                stream.write_u8(AMF0_AVMPLUS)?;
+                Ok(amf.encode(stream)?)
+            }
         }
     }
     pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, Amf0Error> {
+        Self::decode_with(stream, &mut DecodeBudget::default())
+    }
+    /// The object end marker is only valid after the props of an object, here it is `InvalidId`
+    pub(crate) fn decode_with<T: ReadBytesExt>(
+        stream: &mut T,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, Amf0Error> {
         let id = stream.read_u8()?;
 
         match id {
-            AMF0_STRING => Ok(Amf0Value::String(Amf0String::extract(stream)?)),
+            AMF0_STRING => Ok(Amf0Value::String(Amf0String::extract_with(stream, budget)?)),
             AMF0_BOOL => Ok(Amf0Value::Bool(Amf0Bool::extract(stream)?)),
             AMF0_NUMBER => Ok(Amf0Value::Number(Amf0Number::extract(stream)?)),
+            AMF0_OBJECT => Ok(Amf0Value::Object(Amf0Object::extract_with(stream, budget)?)),
+            AMF0_STRICT_ARRAY => Ok(Amf0Value::StrictArray(Amf0StrictArray::extract_with(
+                stream, budget,
+            )?)),
+            AMF0_NULL => Ok(Amf0Value::Null),
+            AMF0_UNDEFINED => Ok(Amf0Value::Undefined),
+            AMF0_UNSUPPORTED => Ok(Amf0Value::Unsupported),
+            AMF0_REFERENCE => Ok(Amf0Value::Reference(Amf0Reference::extract(stream)?)),
+            AMF0_ECMA_ARRAY => Ok(Amf0Value::EcmaArray(Amf0EcmaArray::extract_with(
+                stream, budget,
+            )?)),
+            AMF0_DATE => Ok(Amf0Value::Date(Amf0Date::extract(stream)?)),
+            AMF0_LONG_STRING => Ok(Amf0Value::LongString(Amf0LongString::extract_with(
+                stream, budget,
+            )?)),
+            AMF0_XML_DOCUMENT => Ok(Amf0Value::XmlDocument(Amf0XmlDocument::extract_with(
+                stream, budget,
+            )?)),
+            AMF0_TYPED_OBJECT => Ok(Amf0Value::TypedObject(Amf0TypedObject::extract_with(
+                stream, budget,
+            )?)),
+            AMF0_AVMPLUS => Ok(Amf0Value::Amf3(Amf3Value::decode_with(stream, budget)?)),
             _ => Err(Amf0Error::InvalidId),
         }
     }
@@ -346,4 +997,28 @@ pub enum Amf0Error {
 
     #[error("IO Error: {0}")]
     IoError(#[from] std::io::Error),
+
+    /// A `DecodeLimits` bound, named by the field, was exceeded
+    #[error("Decode limit exceeded: {0}")]
+    LimitExceeded(&'static str),
+
+    #[error("Truncated: expected {expected} bytes, got {got}")]
+    Truncated { expected: usize, got: usize },
+
+    #[error("Amf3 Error: {0}")]
+    Amf3Error(#[from] Amf3Error),
+
+    /// Message of a `Serialize`/`Deserialize` implementation, with the `serde` feature
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl Amf0Error {
+    /// A `DecodeLimits` bound was exceeded, in AMF0 or in an AMF3 value
+    pub fn is_limit(&self) -> bool {
+        matches!(
+            self,
+            Self::LimitExceeded(_) | Self::Amf3Error(Amf3Error::LimitExceeded(_))
+        )
+    }
 }
@@ -1,144 +1,281 @@
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use num_enum::{IntoPrimitive, TryFromPrimitive};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
+use num_enum::{FromPrimitive, IntoPrimitive};
 
 use crate::{
-    error::FlvError,
+    error::{ErrorContext, FlvError},
     v1::{
-        audio::{AudioData, FlvAudioTag},
-        script::{Amf0EcmaArray, Amf0String, FlvScriptTag},
-        video::{FlvVideoData, VideoData},
+        Payload,
+        audio::{AudioData, AudioDataRef, FlvAudioTag, FlvAudioTagRef},
+        encryption::{EncryptionTagHeader, split_encryption_header},
+        limits::{DecodeBudget, DecodeLimits},
+        payload_from_slice, read_payload, read_u32_be,
+        script::FlvScriptTag,
+        take,
+        timestamp::FlvTimestamp,
+        video::{FlvVideoData, FlvVideoDataRef, VideoData, VideoDataRef},
     },
 };
 
-#[derive(Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, IntoPrimitive, FromPrimitive, Clone, Copy)]
 #[repr(u8)]
 pub enum FlvTagType {
     Video = 9,
     Audio = 8,
     ScriptData = 18,
+
+    /// Not defined by the spec (or with the reserved bits set), the raw TagType byte, the data is
+    /// kept as `FlvTagData::Unknown`
+    #[num_enum(catch_all)]
+    Unknown(u8),
 }
 
+/// Reserved UB[2] of the TagType byte, always 0
+pub(crate) const FLV_TAG_RESERVED_BITS: u8 = 0b1100_0000;
 
-#[derive(Debug)]
-pub struct FlvTag {
-    pub tag_type: FlvTagType,
+/// Filter UB[1] of the TagType byte, set if the tag has an `EncryptionTagHeader`
+const FLV_TAG_FILTER_BIT: u8 = 0b0010_0000;
+
+/// TagType UB[5] of the TagType byte
+const FLV_TAG_TYPE_BITS: u8 = 0b0001_1111;
+
+/// Tag type of a TagType byte without the filter bit, a byte that is not a known type (reserved
+/// bits included) is kept whole as `Unknown` so it is encoded again exactly
+pub(crate) fn tag_type_of(byte: u8) -> FlvTagType {
+    if byte & FLV_TAG_RESERVED_BITS != 0 {
+        return FlvTagType::Unknown(byte);
+    }
 
-    /// U24; len of the data in the data field, ONLY THE LEN OF THE DATA DO NOT INCLUDE THE LEN OF
-    /// THE HEADER
-    pub data_size: u32,
+    match FlvTagType::from(byte & FLV_TAG_TYPE_BITS) {
+        FlvTagType::Unknown(_) => FlvTagType::Unknown(byte),
+        tag_type => tag_type,
+    }
+}
 
-    /// U24 (timestamp) + U8 (timestamp_extended)
-    pub timestamp: u32,
+/// The Filter bit is set, it only has a meaning for the tag types of the spec
+fn is_filtered_type(byte: u8, tag_type: FlvTagType) -> bool {
+    byte & FLV_TAG_FILTER_BIT != 0 && !matches!(tag_type, FlvTagType::Unknown(_))
+}
+
+#[derive(Debug, Clone)]
+pub struct FlvTag {
+    /// SI24 (timestamp) + UI8 (timestamp_extended)
+    pub timestamp: FlvTimestamp,
 
     /// U24
     pub stream_id: u32,
 
-    pub data: FlvTagData,
+    /// Present if the Filter bit is set, the body of `data` is then encrypted
+    pub encryption: Option<EncryptionTagHeader>,
 
-    pub previous_tag_size: u32,
+    pub data: FlvTagData,
 }
 
 impl FlvTag {
-    pub fn new_script(script: FlvScriptTag, timestamp: u32) -> Self {
-        let tag_data = FlvTagData::Script(script);
-        let size = tag_data.size() as u32;
-
+    pub fn new_script(script: FlvScriptTag, timestamp: impl Into<FlvTimestamp>) -> Self {
         Self {
-            tag_type: FlvTagType::ScriptData,
-            data_size: size,
-            timestamp,
+            timestamp: timestamp.into(),
             stream_id: 0,
-            data: tag_data,
-            previous_tag_size: calc_previous_tag_size(size),
+            encryption: None,
+            data: FlvTagData::Script(script),
         }
     }
 
-    pub fn new_audio(audio: FlvAudioTag, timestamp: u32) -> Self {
-        let tag_data = FlvTagData::Audio(audio);
-        let size = tag_data.size() as u32;
-
+    pub fn new_audio(audio: FlvAudioTag, timestamp: impl Into<FlvTimestamp>) -> Self {
         Self {
-            tag_type: FlvTagType::Audio,
-            data_size: size,
-            timestamp,
+            timestamp: timestamp.into(),
             stream_id: 0,
-            data: tag_data,
-            previous_tag_size: calc_previous_tag_size(size),
+            encryption: None,
+            data: FlvTagData::Audio(audio),
         }
     }
 
-    pub fn new_video(video: FlvVideoData, timestamp: u32) -> Self {
-        let tag_data = FlvTagData::Video(video);
-        let size = tag_data.size() as u32;
-
+    pub fn new_video(video: FlvVideoData, timestamp: impl Into<FlvTimestamp>) -> Self {
         Self {
-            tag_type: FlvTagType::Video,
-            data_size: size,
-            timestamp,
+            timestamp: timestamp.into(),
             stream_id: 0,
-            data: tag_data,
-            previous_tag_size: calc_previous_tag_size(size),
+            encryption: None,
+            data: FlvTagData::Video(video),
         }
     }
 
+    /// TagType, it is always the type of `data`
+    pub const fn tag_type(&self) -> FlvTagType {
+        self.data.tag_type()
+    }
+
+    /// The Filter bit is set: the tag has an `EncryptionTagHeader`
+    pub const fn is_filtered(&self) -> bool {
+        self.encryption.is_some()
+    }
+
+    /// DataSize: length of the data as it is encoded, including the `EncryptionTagHeader`
+    pub fn data_size(&self) -> usize {
+        let encryption = self.encryption.as_ref();
+
+        self.data.encoded_len() + encryption.map_or(0, EncryptionTagHeader::encoded_len)
+    }
+
+    /// Tag header (11) + data + PreviousTagSize (4)
+    pub fn encoded_len(&self) -> usize {
+        FLV_TAG_HEADER_SIZE + self.data_size() + 4
+    }
+
     pub fn decode<T: ReadBytesExt>(stream: &mut T) -> Result<Self, FlvError> {
-        let tag_type = stream.read_u8()?;
+        Self::decode_with_limits(stream, &DecodeLimits::default())
+    }
+    /// Decodes a tag from untrusted input, a tag that goes over `limits` is an error
+    pub fn decode_with_limits<T: ReadBytesExt>(
+        stream: &mut T,
+        limits: &DecodeLimits,
+    ) -> Result<Self, FlvError> {
+        let tag_type_byte = stream.read_u8().in_field("tag header")?;
+
+        let data_size = stream.read_u24::<BigEndian>().in_field("tag header")?;
+        let timestamp = stream.read_u24::<BigEndian>().in_field("tag header")?;
+        let timestamp_extended = stream.read_u8().in_field("tag header")?;
+        let timestamp = FlvTimestamp::from_parts(timestamp, timestamp_extended);
 
-        let data_size = stream.read_u24::<BigEndian>()?;
-        let timestamp = stream.read_u32::<BigEndian>()?;
+        let stream_id = stream.read_u24::<BigEndian>().in_field("tag header")?;
+        let tag_type = tag_type_of(tag_type_byte);
+        let filter = is_filtered_type(tag_type_byte, tag_type);
 
-        let stream_id = stream.read_u24::<BigEndian>()?;
-        let tag_type =  FlvTagType::try_from_primitive(tag_type)?;
+        limits.check_tag_size(data_size).in_field("tag header")?;
 
-        let data = FlvTagData::decode(stream, tag_type, data_size)?;
+        // the encrypted body of script data can't be decoded as AMF0
+        let mut data = if filter && tag_type == FlvTagType::ScriptData {
+            let data = read_payload(stream, data_size as usize).in_field("script data")?;
 
-        let previous_tag_size = stream.read_u32::<BigEndian>()?;
+            FlvTagData::EncryptedScript(data)
+        } else {
+            FlvTagData::decode_with(stream, tag_type, data_size, &mut DecodeBudget::new(*limits))?
+        };
+
+        // the encryption header is decoded as the start of the payload and split from it
+        let encryption = match data.payload_mut() {
+            Some(payload) if filter => {
+                Some(split_encryption_header(payload).in_field("encryption header")?)
+            }
+            _ => None,
+        };
+
+        // PreviousTagSize is computed again when the tag is encoded
+        read_u32_be(stream).in_field("previous tag size")?;
 
         Ok(Self {
-            tag_type,
-            data_size,
             timestamp,
             stream_id,
+            encryption,
             data,
-            previous_tag_size,
         })
     }
+    /// DataSize and PreviousTagSize are computed from the data
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), FlvError> {
-        stream.write_u8(self.tag_type.into())?;
-
-        stream.write_u24::<BigEndian>(self.data_size)?;
-
-        stream.write_u32::<BigEndian>(self.timestamp)?;
-
-        stream.write_u24::<BigEndian>(self.stream_id)?;
+        let mut buffer = Vec::with_capacity(self.data_size());
 
-        self.data.encode(stream)?;
-
-        stream.write_u32::<BigEndian>(self.previous_tag_size)?;
+        encode_tag(
+            stream,
+            &mut buffer,
+            &self.data,
+            self.encryption.as_ref(),
+            self.timestamp,
+            self.stream_id,
+        )?;
 
         Ok(())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum FlvTagData {
     Video(FlvVideoData),
     Audio(FlvAudioTag),
     Script(FlvScriptTag),
+
+    /// Script data of a filtered tag, only the encrypted body without the encryption header
+    EncryptedScript(Payload),
+
+    /// Data of a tag type not defined by the spec with its raw TagType byte, kept as it is to pass
+    /// it through
+    Unknown(u8, Payload),
 }
 
 impl FlvTagData {
-    pub const fn size(&self) -> usize {
+    pub const fn tag_type(&self) -> FlvTagType {
+        match self {
+            Self::Video(_) => FlvTagType::Video,
+            Self::Audio(_) => FlvTagType::Audio,
+            Self::Script(_) | Self::EncryptedScript(_) => FlvTagType::ScriptData,
+            Self::Unknown(tag_type, _) => FlvTagType::Unknown(*tag_type),
+        }
+    }
+    /// Length of the data as it is encoded, without the `EncryptionTagHeader` of a filtered tag
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            Self::Video(video) => video.encoded_len(),
+            Self::Audio(audio) => audio.encoded_len(),
+            Self::Script(script) => script.encoded_len(),
+            Self::EncryptedScript(data) | Self::Unknown(_, data) => data.len(),
+        }
+    }
+    /// Size of the audio/video tag header at the start of the data, the `EncryptionTagHeader` of a
+    /// filtered tag goes right after it
+    pub const fn header_size(&self) -> usize {
         match self {
-            Self::Video(video) => video.size(),
-            Self::Audio(audio) => audio.size(),
-            Self::Script(script) => script.size(),
+            Self::Video(video) => match video.video_data {
+                // FrameType/CodecId (1) + AVCPacketType (1) + CompositionTime (3)
+                VideoData::Avc(_) => 5,
+                VideoData::Other(_) => 1,
+            },
+            Self::Audio(audio) => match audio.data {
+                // SoundInfo (1) + AACPacketType (1)
+                AudioData::Aac(_) => 2,
+                AudioData::Other(_) => 1,
+            },
+            Self::Script(_) | Self::EncryptedScript(_) | Self::Unknown(..) => 0,
+        }
+    }
+    /// Audio/video payload after the tag header, `None` for decoded script data
+    pub fn payload(&self) -> Option<&Payload> {
+        match self {
+            Self::Video(video) => Some(match &video.video_data {
+                VideoData::Avc(avc) => &avc.data,
+                VideoData::Other(data) => data,
+            }),
+            Self::Audio(audio) => Some(match &audio.data {
+                AudioData::Aac(aac) => &aac.data,
+                AudioData::Other(data) => data,
+            }),
+            Self::Script(_) => None,
+            Self::EncryptedScript(data) | Self::Unknown(_, data) => Some(data),
+        }
+    }
+    pub fn payload_mut(&mut self) -> Option<&mut Payload> {
+        match self {
+            Self::Video(video) => Some(match &mut video.video_data {
+                VideoData::Avc(avc) => &mut avc.data,
+                VideoData::Other(data) => data,
+            }),
+            Self::Audio(audio) => Some(match &mut audio.data {
+                AudioData::Aac(aac) => &mut aac.data,
+                AudioData::Other(data) => data,
+            }),
+            Self::Script(_) => None,
+            Self::EncryptedScript(data) | Self::Unknown(_, data) => Some(data),
         }
     }
     pub fn decode<T: ReadBytesExt>(
         stream: &mut T,
         tag_type: FlvTagType,
         data_size: u32,
+    ) -> Result<Self, FlvError> {
+        Self::decode_with(stream, tag_type, data_size, &mut DecodeBudget::default())
+    }
+    /// The payloads are not accounted in `budget`, they are bounded by `data_size`
+    pub(crate) fn decode_with<T: ReadBytesExt>(
+        stream: &mut T,
+        tag_type: FlvTagType,
+        data_size: u32,
+        budget: &mut DecodeBudget,
     ) -> Result<Self, FlvError> {
         match tag_type {
             FlvTagType::Video => Ok(Self::Video(FlvVideoData::decode(stream, data_size)?)),
@@ -148,10 +285,16 @@ impl FlvTagData {
                 Ok(Self::Audio(tag))
             }
             FlvTagType::ScriptData => {
-                let tag = FlvScriptTag::decode(stream, data_size as usize)?;
+                let tag = FlvScriptTag::decode_with(stream, data_size as usize, budget)
+                    .in_field("script data")?;
 
                 Ok(Self::Script(tag))
             }
+            FlvTagType::Unknown(tag_type) => {
+                let data = read_payload(stream, data_size as usize).in_field("tag data")?;
+
+                Ok(Self::Unknown(tag_type, data))
+            }
         }
     }
     pub fn encode<T: WriteBytesExt>(&self, stream: &mut T) -> Result<(), FlvError> {
@@ -159,14 +302,318 @@ impl FlvTagData {
             Self::Video(data) => data.encode(stream)?,
             Self::Audio(data) => data.encode(stream)?,
             Self::Script(data) => data.encode(stream)?,
+            Self::EncryptedScript(data) | Self::Unknown(_, data) => stream.write_all(data)?,
         }
 
         Ok(())
     }
 }
 
+#[cfg(feature = "bytes")]
+impl FlvTag {
+    /// Decodes the tag at the start of `frame` without copying, the payloads are slices of `frame`
+    pub fn decode_bytes(frame: &bytes::Bytes) -> Result<Self, FlvError> {
+        Self::decode_bytes_with_limits(frame, &DecodeLimits::default())
+    }
+    pub fn decode_bytes_with_limits(
+        frame: &bytes::Bytes,
+        limits: &DecodeLimits,
+    ) -> Result<Self, FlvError> {
+        let (tag, _) = FlvTagRef::parse_with_limits(frame, limits)?;
+
+        Ok(tag.owned_with(&|data| frame.slice_ref(data)))
+    }
+}
+
+/// Borrowed version of `FlvTag` parsed from a byte slice, audio and video payloads point into the
+/// slice instead of being copied
+#[derive(Debug, Clone)]
+pub struct FlvTagRef<'a> {
+    /// SI24 (timestamp) + UI8 (timestamp_extended)
+    pub timestamp: FlvTimestamp,
+
+    /// U24
+    pub stream_id: u32,
+
+    pub encryption: Option<EncryptionTagHeader>,
+
+    pub data: FlvTagDataRef<'a>,
+}
+
+impl<'a> FlvTagRef<'a> {
+    /// Parses the tag at the start of `input`, returns it with the bytes after it
+    pub fn parse(input: &'a [u8]) -> Result<(Self, &'a [u8]), FlvError> {
+        Self::parse_with_limits(input, &DecodeLimits::default())
+    }
+    /// The payloads are not copied, `limits` bounds the tag size and the script data
+    pub fn parse_with_limits(
+        input: &'a [u8],
+        limits: &DecodeLimits,
+    ) -> Result<(Self, &'a [u8]), FlvError> {
+        let mut input = input;
+        let header = take(&mut input, FLV_TAG_HEADER_SIZE).in_field("tag header")?;
+
+        let tag_type = tag_type_of(header[0]);
+        let filter = is_filtered_type(header[0], tag_type);
+        let data_size = BigEndian::read_u24(&header[1..4]);
+        let timestamp = FlvTimestamp::from_parts(BigEndian::read_u24(&header[4..7]), header[7]);
+        let stream_id = BigEndian::read_u24(&header[8..11]);
+
+        limits.check_tag_size(data_size).in_field("tag header")?;
+
+        let data = take(&mut input, data_size as usize).in_field("tag data")?;
+
+        let mut data = if filter && tag_type == FlvTagType::ScriptData {
+            FlvTagDataRef::EncryptedScript(data)
+        } else {
+            FlvTagDataRef::parse_with(data, tag_type, &mut DecodeBudget::new(*limits))?
+        };
+
+        let encryption = match data.payload_mut() {
+            Some(payload) if filter => {
+                Some(EncryptionTagHeader::decode(payload).in_field("encryption header")?)
+            }
+            _ => None,
+        };
+
+        take(&mut input, 4).in_field("previous tag size")?;
+
+        Ok((
+            Self {
+                timestamp,
+                stream_id,
+                encryption,
+                data,
+            },
+            input,
+        ))
+    }
+    /// TagType, it is always the type of `data`
+    pub const fn tag_type(&self) -> FlvTagType {
+        self.data.tag_type()
+    }
+    pub fn to_owned(&self) -> FlvTag {
+        self.owned_with(&payload_from_slice)
+    }
+    /// `payload` turns the borrowed payloads into the owned ones
+    pub(crate) fn owned_with(&self, payload: &impl Fn(&'a [u8]) -> Payload) -> FlvTag {
+        FlvTag {
+            timestamp: self.timestamp,
+            stream_id: self.stream_id,
+            encryption: self.encryption.clone(),
+            data: self.data.owned_with(payload),
+        }
+    }
+}
+
+/// Script data is always decoded into the owned types, AMF0 values are not stored as bytes
+#[derive(Debug, Clone)]
+pub enum FlvTagDataRef<'a> {
+    Video(FlvVideoDataRef<'a>),
+    Audio(FlvAudioTagRef<'a>),
+    Script(FlvScriptTag),
+    EncryptedScript(&'a [u8]),
+    Unknown(u8, &'a [u8]),
+}
+
+impl<'a> FlvTagDataRef<'a> {
+    pub const fn tag_type(&self) -> FlvTagType {
+        match self {
+            Self::Video(_) => FlvTagType::Video,
+            Self::Audio(_) => FlvTagType::Audio,
+            Self::Script(_) | Self::EncryptedScript(_) => FlvTagType::ScriptData,
+            Self::Unknown(tag_type, _) => FlvTagType::Unknown(*tag_type),
+        }
+    }
+    /// `data` must be exactly the data of the tag
+    pub fn parse(data: &'a [u8], tag_type: FlvTagType) -> Result<Self, FlvError> {
+        Self::parse_with(data, tag_type, &mut DecodeBudget::default())
+    }
+    pub(crate) fn parse_with(
+        data: &'a [u8],
+        tag_type: FlvTagType,
+        budget: &mut DecodeBudget,
+    ) -> Result<Self, FlvError> {
+        match tag_type {
+            FlvTagType::Video => Ok(Self::Video(FlvVideoDataRef::parse(data)?)),
+            FlvTagType::Audio => Ok(Self::Audio(FlvAudioTagRef::parse(data)?)),
+            FlvTagType::ScriptData => {
+                let tag = FlvScriptTag::decode_with(&mut &data[..], data.len(), budget)
+                    .in_field("script data")?;
+
+                Ok(Self::Script(tag))
+            }
+            FlvTagType::Unknown(tag_type) => Ok(Self::Unknown(tag_type, data)),
+        }
+    }
+    /// Audio/video payload after the tag header, `None` for decoded script data
+    pub fn payload_mut(&mut self) -> Option<&mut &'a [u8]> {
+        match self {
+            Self::Video(video) => Some(match &mut video.video_data {
+                VideoDataRef::Avc(avc) => &mut avc.data,
+                VideoDataRef::Other(data) => data,
+            }),
+            Self::Audio(audio) => Some(match &mut audio.data {
+                AudioDataRef::Aac(aac) => &mut aac.data,
+                AudioDataRef::Other(data) => data,
+            }),
+            Self::Script(_) => None,
+            Self::EncryptedScript(data) | Self::Unknown(_, data) => Some(data),
+        }
+    }
+    pub fn to_owned(&self) -> FlvTagData {
+        self.owned_with(&payload_from_slice)
+    }
+    pub(crate) fn owned_with(&self, payload: &impl Fn(&'a [u8]) -> Payload) -> FlvTagData {
+        match self {
+            Self::Video(video) => FlvTagData::Video(video.owned_with(payload)),
+            Self::Audio(audio) => FlvTagData::Audio(audio.owned_with(payload)),
+            Self::Script(script) => FlvTagData::Script(script.clone()),
+            Self::EncryptedScript(data) => FlvTagData::EncryptedScript(payload(data)),
+            Self::Unknown(tag_type, data) => FlvTagData::Unknown(*tag_type, payload(data)),
+        }
+    }
+}
+
+/// Iterator over the tags of a byte slice that starts right after the header and the
+/// `PreviousTagSize0`, it stops after the first error
+#[derive(Debug, Clone)]
+pub struct FlvTagRefIter<'a> {
+    input: &'a [u8],
+
+    /// Index of the next tag
+    index: u64,
+
+    /// Offset of the next tag from the start of the file
+    offset: u64,
+}
+
+impl<'a> FlvTagRefIter<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        Self::with_offset(input, 0)
+    }
+
+    /// `offset` is the position of `input` in the file, it is used in the errors
+    pub fn with_offset(input: &'a [u8], offset: u64) -> Self {
+        Self {
+            input,
+            index: 0,
+            offset,
+        }
+    }
+
+    /// Bytes not parsed yet
+    pub fn remaining(&self) -> &'a [u8] {
+        self.input
+    }
+}
+
+impl<'a> Iterator for FlvTagRefIter<'a> {
+    type Item = Result<FlvTagRef<'a>, FlvError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.input.is_empty() {
+            return None;
+        }
+
+        match FlvTagRef::parse(self.input) {
+            Ok((tag, rest)) => {
+                self.offset += (self.input.len() - rest.len()) as u64;
+                self.index += 1;
+                self.input = rest;
+                Some(Ok(tag))
+            }
+            Err(e) => {
+                let tag_type = Some(tag_type_of(self.input[0]));
+
+                self.input = &[];
+                Some(Err(e.in_tag(self.index, self.offset, tag_type)))
+            }
+        }
+    }
+}
+
+/// TagType (1) + DataSize (3) + Timestamp (3) + TimestampExtended (1) + StreamID (3)
+pub const FLV_TAG_HEADER_SIZE: usize = 11;
+
+/// Max value of the U24 DataSize field
+pub const FLV_TAG_MAX_DATA_SIZE: u32 = 0xFF_FFFF;
+
+/// Encodes the 11 bytes of a tag header, the timestamp is split in its lower 24 bits followed by
+/// the upper 8 bits (TimestampExtended), `filter` sets the Filter bit
+pub fn encode_tag_header<T: WriteBytesExt>(
+    stream: &mut T,
+    tag_type: FlvTagType,
+    filter: bool,
+    data_size: u32,
+    timestamp: FlvTimestamp,
+    stream_id: u32,
+) -> Result<(), FlvError> {
+    if data_size > FLV_TAG_MAX_DATA_SIZE {
+        return Err(FlvError::TagTooLarge(data_size as usize));
+    }
+
+    let filter = if filter { FLV_TAG_FILTER_BIT } else { 0 };
+
+    stream.write_u8(u8::from(tag_type) | filter)?;
+    stream.write_u24::<BigEndian>(data_size)?;
+    stream.write_u24::<BigEndian>(timestamp.lower())?;
+    stream.write_u8(timestamp.extended())?;
+    stream.write_u24::<BigEndian>(stream_id & 0xFF_FFFF)?;
+
+    Ok(())
+}
+
 /// SIZE MUST BE WITHOUT THE HEADER SIZE
 #[inline]
 pub fn calc_previous_tag_size(size: u32) -> u32 {
-    size + 11
+    size + FLV_TAG_HEADER_SIZE as u32
+}
+
+/// Encodes a whole tag (header, data and previous tag size) using `buffer` to measure the data,
+/// returns the number of bytes written
+pub(crate) fn encode_tag<W: WriteBytesExt>(
+    stream: &mut W,
+    buffer: &mut Vec<u8>,
+    data: &FlvTagData,
+    encryption: Option<&EncryptionTagHeader>,
+    timestamp: FlvTimestamp,
+    stream_id: u32,
+) -> Result<usize, FlvError> {
+    buffer.clear();
+    data.encode(buffer)?;
+
+    if let Some(encryption) = encryption {
+        insert_encryption_header(buffer, data.header_size(), encryption)?;
+    }
+
+    let data_size = u32::try_from(buffer.len()).map_err(|_| FlvError::TagTooLarge(buffer.len()))?;
+
+    encode_tag_header(
+        stream,
+        data.tag_type(),
+        encryption.is_some(),
+        data_size,
+        timestamp,
+        stream_id,
+    )?;
+    stream.write_all(buffer)?;
+    stream.write_u32::<BigEndian>(calc_previous_tag_size(data_size))?;
+
+    Ok(calc_previous_tag_size(data_size) as usize + 4)
+}
+
+/// Inserts `encryption` in the encoded `data` of a tag, after its `header_size` bytes of
+/// audio/video tag header
+fn insert_encryption_header(
+    data: &mut Vec<u8>,
+    header_size: usize,
+    encryption: &EncryptionTagHeader,
+) -> Result<(), FlvError> {
+    let mut header = Vec::with_capacity(encryption.encoded_len());
+    encryption.encode(&mut header)?;
+
+    data.splice(header_size..header_size, header);
+
+    Ok(())
 }
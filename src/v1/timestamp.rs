@@ -0,0 +1,168 @@
+use std::{
+    ops::{Add, AddAssign, Sub, SubAssign},
+    time::Duration,
+};
+
+/// Half of the range of a timestamp, a jump bigger than this is taken as a rollover
+const FLV_TIMESTAMP_HALF_RANGE: u32 = 1 << 31;
+
+/// FlvTimestamp: Time of a tag in milliseconds, stored in the tag header as SI24 (lower 24 bits)
+/// followed by UI8 (TimestampExtended, upper 8 bits)
+///
+/// The 32 bits roll over after ~49.7 days, adding or subtracting a `Duration` wraps around like
+/// the field does, use `TimestampUnwrapper` to get a monotonic time for longer streams
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FlvTimestamp(pub u32);
+
+impl FlvTimestamp {
+    pub const ZERO: Self = Self(0);
+
+    pub const fn from_millis(millis: u32) -> Self {
+        Self(millis)
+    }
+
+    /// `lower` is the SI24 field (only its lower 24 bits are used), `extended` is
+    /// TimestampExtended
+    pub const fn from_parts(lower: u32, extended: u8) -> Self {
+        Self((extended as u32) << 24 | lower & 0xFF_FFFF)
+    }
+
+    pub const fn as_millis(self) -> u32 {
+        self.0
+    }
+
+    /// SI24: lower 24 bits
+    pub const fn lower(self) -> u32 {
+        self.0 & 0xFF_FFFF
+    }
+
+    /// UI8 TimestampExtended: upper 8 bits
+    pub const fn extended(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    pub const fn to_duration(self) -> Duration {
+        Duration::from_millis(self.0 as u64)
+    }
+
+    /// Time from `earlier` to `self`, zero if `earlier` is later (as `Instant::duration_since`)
+    pub const fn duration_since(self, earlier: Self) -> Duration {
+        Duration::from_millis(self.0.saturating_sub(earlier.0) as u64)
+    }
+
+    pub fn checked_add(self, duration: Duration) -> Option<Self> {
+        let millis = u32::try_from(duration.as_millis()).ok()?;
+
+        self.0.checked_add(millis).map(Self)
+    }
+
+    pub fn checked_sub(self, duration: Duration) -> Option<Self> {
+        let millis = u32::try_from(duration.as_millis()).ok()?;
+
+        self.0.checked_sub(millis).map(Self)
+    }
+}
+
+/// Milliseconds of `duration` modulo 2^32, as the timestamp field wraps
+fn wrapping_millis(duration: Duration) -> u32 {
+    duration.as_millis() as u32
+}
+
+impl From<u32> for FlvTimestamp {
+    fn from(millis: u32) -> Self {
+        Self(millis)
+    }
+}
+
+impl From<FlvTimestamp> for u32 {
+    fn from(timestamp: FlvTimestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl From<FlvTimestamp> for Duration {
+    fn from(timestamp: FlvTimestamp) -> Self {
+        timestamp.to_duration()
+    }
+}
+
+/// Fails if the duration doesn't fit in 32 bits of milliseconds, sub-millisecond precision is
+/// truncated
+impl TryFrom<Duration> for FlvTimestamp {
+    type Error = std::num::TryFromIntError;
+
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        u32::try_from(duration.as_millis()).map(Self)
+    }
+}
+
+impl Add<Duration> for FlvTimestamp {
+    type Output = Self;
+
+    fn add(self, duration: Duration) -> Self {
+        Self(self.0.wrapping_add(wrapping_millis(duration)))
+    }
+}
+
+impl AddAssign<Duration> for FlvTimestamp {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for FlvTimestamp {
+    type Output = Self;
+
+    fn sub(self, duration: Duration) -> Self {
+        Self(self.0.wrapping_sub(wrapping_millis(duration)))
+    }
+}
+
+impl SubAssign<Duration> for FlvTimestamp {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+/// TimestampUnwrapper: Turns the 32-bit timestamps of a stream into a monotonic time that keeps
+/// counting after they roll over
+///
+/// A timestamp more than half the range (~24.8 days) before the previous one is taken as a
+/// rollover, one more than half the range after it as a late tag from before the last rollover
+#[derive(Debug, Default, Clone)]
+pub struct TimestampUnwrapper {
+    /// Previous timestamp
+    last: Option<u32>,
+
+    /// Number of rollovers seen so far
+    rollovers: u64,
+}
+
+impl TimestampUnwrapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time of `timestamp` counting the rollovers seen so far
+    pub fn unwrap(&mut self, timestamp: FlvTimestamp) -> Duration {
+        let millis = timestamp.as_millis();
+
+        let rollovers = match self.last {
+            Some(last) if last > millis && last - millis > FLV_TIMESTAMP_HALF_RANGE => {
+                self.rollovers += 1;
+                self.rollovers
+            }
+            // late tag, it doesn't move the stream back
+            Some(last) if millis > last && millis - last > FLV_TIMESTAMP_HALF_RANGE => {
+                return Duration::from_millis(
+                    self.rollovers.saturating_sub(1) << 32 | millis as u64,
+                );
+            }
+            _ => self.rollovers,
+        };
+
+        self.last = Some(millis);
+
+        Duration::from_millis(rollovers << 32 | millis as u64)
+    }
+}
@@ -1,9 +1,12 @@
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use num_enum::{IntoPrimitive, TryFromPrimitive};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
+use num_enum::{FromPrimitive, IntoPrimitive};
 
-use crate::error::FlvError;
+use crate::{
+    error::{ErrorContext, FlvError},
+    v1::{Payload, payload_from_slice, payload_size, read_payload, take},
+};
 
-#[derive(Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, IntoPrimitive, FromPrimitive, Clone, Copy)]
 #[repr(u8)]
 pub enum FrameType {
     Keyframe = 0x1,
@@ -11,9 +14,12 @@ pub enum FrameType {
     DisposableInterFrame = 0x3,
     GeneratedKeyFrame = 0x4,
     VideoInfo = 0x5,
+
+    #[num_enum(catch_all)]
+    Unknown(u8),
 }
 
-#[derive(Debug, Eq, PartialEq, IntoPrimitive, TryFromPrimitive, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, IntoPrimitive, FromPrimitive, Clone, Copy)]
 #[repr(u8)]
 pub enum CodecId {
     Jpeg = 0x1,
@@ -23,9 +29,13 @@ pub enum CodecId {
     Vp6Alpha = 0x5,
     ScreenVideoV2 = 0x6,
     Avc = 0x7,
+
+    /// Not defined by the spec, the video data is kept as `VideoData::Other`
+    #[num_enum(catch_all)]
+    Unknown(u8),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct FlvVideoData {
     pub frame_type: FrameType,
     pub codec: CodecId,
@@ -35,14 +45,25 @@ pub struct FlvVideoData {
 }
 
 impl FlvVideoData {
-    pub const fn size(&self) -> usize {
-        self.video_data.size()
+    /// AVC decoder configuration, it must come before the first frame
+    pub fn is_sequence_header(&self) -> bool {
+        match &self.video_data {
+            VideoData::Avc(avc) => avc.packet_type == AvcPacketType::SEQUENCE_HEADER,
+            VideoData::Other(_) => false,
+        }
+    }
+    /// FrameType/CodecId (1) + video data
+    pub const fn encoded_len(&self) -> usize {
+        1 + self.video_data.encoded_len()
     }
     pub fn decode<T: ReadBytesExt>(stream: &mut T, data_size: u32) -> Result<Self, FlvError> {
-        let frame_codec = stream.read_u8()?;
+        // the size is checked before reading, so a short tag doesn't read into the next one
+        payload_size(data_size as usize, 1).in_field("video header")?;
 
-        let frame = FrameType::try_from_primitive(frame_codec >> 4 & 0x0F)?;
-        let codec = CodecId::try_from_primitive(frame_codec & 0x0F)?;
+        let frame_codec = stream.read_u8().in_field("video header")?;
+
+        let frame = FrameType::from(frame_codec >> 4 & 0x0F);
+        let codec = CodecId::from(frame_codec & 0x0F);
 
         let video_data = VideoData::decode(stream, data_size as usize, codec)?;
 
@@ -63,16 +84,16 @@ impl FlvVideoData {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum VideoData {
     Avc(AvcVideoPacket),
-    Other(Vec<u8>),
+    Other(Payload),
 }
 
 impl VideoData {
-    pub const fn size(&self) -> usize {
+    pub const fn encoded_len(&self) -> usize {
         match self {
-            VideoData::Avc(avc) => avc.size(),
+            VideoData::Avc(avc) => avc.encoded_len(),
             VideoData::Other(other) => other.len(),
         }
     }
@@ -84,16 +105,12 @@ impl VideoData {
         match codec {
             CodecId::Avc => Ok(VideoData::Avc(AvcVideoPacket::decode(stream, data_size)?)),
             _ => {
-                let mut v = vec![0_u8; data_size];
-
-                stream.read(&mut v)?;
-
-                let mut a = vec![0_u8; 400];
-
-                let n = stream.read(&mut a)?;
+                // data_size includes the FrameType/CodecId byte
+                let size = payload_size(data_size, 1).in_field("video header")?;
 
-                println!("{:?}", &a[..n]);
-                Ok(VideoData::Other(v))
+                Ok(VideoData::Other(
+                    read_payload(stream, size).in_field("video data")?,
+                ))
             }
         }
     }
@@ -102,7 +119,7 @@ impl VideoData {
         match self {
             Self::Avc(v) => v.encode(stream)?,
             Self::Other(v) => {
-                stream.write(&v)?;
+                stream.write_all(v)?;
             }
         }
 
@@ -118,55 +135,51 @@ impl AvcPacketType {
     pub const EOS: u8 = 2;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AvcVideoPacket {
     pub packet_type: u8,
 
     /// I24
     pub composition_time: i32,
 
-    pub data: Vec<u8>,
+    pub data: Payload,
 }
 
 impl AvcVideoPacket {
-    pub fn new_sequence_header(data: Vec<u8>) -> Self {
+    pub fn new_sequence_header(data: impl Into<Payload>) -> Self {
         Self {
             packet_type: AvcPacketType::SEQUENCE_HEADER,
             composition_time: 0,
-            data,
+            data: data.into(),
         }
     }
-    pub fn new_nalu(data: Vec<u8>, composition_time: i32) -> Self {
+    pub fn new_nalu(data: impl Into<Payload>, composition_time: i32) -> Self {
         Self {
             packet_type: AvcPacketType::NALU,
             composition_time,
-            data,
+            data: data.into(),
         }
     }
     pub fn eos() -> Self {
         Self {
             packet_type: AvcPacketType::EOS,
             composition_time: 0,
-            data: Vec::new(),
+            data: Payload::new(),
         }
     }
-    pub const fn size(&self) -> usize {
-        self.data.len() + 5
+    /// AVCPacketType (1) + CompositionTime (3) + data
+    pub const fn encoded_len(&self) -> usize {
+        4 + self.data.len()
     }
     pub fn decode<T: ReadBytesExt>(stream: &mut T, data_size: usize) -> Result<Self, FlvError> {
-        let packet_type = stream.read_u8()?;
+        // FrameType/CodecId (1) + AVCPacketType (1) + CompositionTime (3)
+        let size = payload_size(data_size, 5).in_field("video header")?;
 
-        let composition_time = stream.read_i24::<BigEndian>()?;
+        let packet_type = stream.read_u8().in_field("video header")?;
 
-        let size = if data_size > 5 {
-            data_size - 5
-        } else {
-            data_size
-        };
+        let composition_time = stream.read_i24::<BigEndian>().in_field("video header")?;
 
-        let mut data = vec![0_u8; size];
-
-        stream.read(&mut data)?;
+        let data = read_payload(stream, size).in_field("video data")?;
 
         Ok(Self {
             packet_type,
@@ -180,8 +193,105 @@ impl AvcVideoPacket {
 
         stream.write_i24::<BigEndian>(self.composition_time)?;
 
-        stream.write(&self.data)?;
+        stream.write_all(&self.data)?;
 
         Ok(())
     }
 }
+
+/// Borrowed version of `FlvVideoData`, the payload points into the parsed buffer
+#[derive(Debug, Clone, Copy)]
+pub struct FlvVideoDataRef<'a> {
+    pub frame_type: FrameType,
+    pub codec: CodecId,
+
+    pub video_data: VideoDataRef<'a>,
+}
+
+impl<'a> FlvVideoDataRef<'a> {
+    /// `data` must be the whole data of the tag
+    pub fn parse(data: &'a [u8]) -> Result<Self, FlvError> {
+        let mut data = data;
+        let frame_codec = take(&mut data, 1).in_field("video header")?[0];
+
+        let frame_type = FrameType::from(frame_codec >> 4 & 0x0F);
+        let codec = CodecId::from(frame_codec & 0x0F);
+
+        let video_data = VideoDataRef::parse(data, codec)?;
+
+        Ok(Self {
+            frame_type,
+            codec,
+            video_data,
+        })
+    }
+    pub fn to_owned(&self) -> FlvVideoData {
+        self.owned_with(&payload_from_slice)
+    }
+    /// `payload` turns the borrowed payload into the owned one
+    pub(crate) fn owned_with(&self, payload: &impl Fn(&'a [u8]) -> Payload) -> FlvVideoData {
+        FlvVideoData {
+            frame_type: self.frame_type,
+            codec: self.codec,
+            video_data: self.video_data.owned_with(payload),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum VideoDataRef<'a> {
+    Avc(AvcVideoPacketRef<'a>),
+    Other(&'a [u8]),
+}
+
+impl<'a> VideoDataRef<'a> {
+    /// `data` must be the data of the tag after the FrameType/CodecId byte
+    pub fn parse(data: &'a [u8], codec: CodecId) -> Result<Self, FlvError> {
+        match codec {
+            CodecId::Avc => Ok(Self::Avc(AvcVideoPacketRef::parse(data)?)),
+            _ => Ok(Self::Other(data)),
+        }
+    }
+    pub fn to_owned(&self) -> VideoData {
+        self.owned_with(&payload_from_slice)
+    }
+    pub(crate) fn owned_with(&self, payload: &impl Fn(&'a [u8]) -> Payload) -> VideoData {
+        match self {
+            Self::Avc(avc) => VideoData::Avc(avc.owned_with(payload)),
+            Self::Other(data) => VideoData::Other(payload(data)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AvcVideoPacketRef<'a> {
+    pub packet_type: u8,
+
+    /// I24
+    pub composition_time: i32,
+
+    pub data: &'a [u8],
+}
+
+impl<'a> AvcVideoPacketRef<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, FlvError> {
+        let mut data = data;
+        let header = take(&mut data, 4).in_field("video header")?;
+
+        Ok(Self {
+            packet_type: header[0],
+            composition_time: BigEndian::read_i24(&header[1..]),
+            data,
+        })
+    }
+    pub fn to_owned(&self) -> AvcVideoPacket {
+        self.owned_with(&payload_from_slice)
+    }
+    pub(crate) fn owned_with(&self, payload: &impl Fn(&'a [u8]) -> Payload) -> AvcVideoPacket {
+        AvcVideoPacket {
+            packet_type: self.packet_type,
+            composition_time: self.composition_time,
+            data: payload(self.data),
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::io::{Seek, SeekFrom, Write};
+
+use byteorder::{BigEndian, WriteBytesExt};
+
+use crate::{
+    error::FlvError,
+    v1::{
+        audio::FlvAudioTag,
+        encryption::EncryptionTagHeader,
+        header::FlvHeader,
+        script::{
+            Amf0DataObjectProp, Amf0Key, Amf0Number, Amf0Object, Amf0StrictArray, Amf0String,
+            Amf0Value, FLV_SCRIPT_ON_METADATA, FlvScriptTag,
+        },
+        tag::{FlvTag, FlvTagData, encode_tag},
+        timestamp::FlvTimestamp,
+        video::{FlvVideoData, FrameType},
+    },
+};
+
+const METADATA_PADDING: &str = "padding";
+
+/// Key (2 + 7) + empty string (1 + 2) of a padding prop
+const METADATA_PADDING_OVERHEAD: usize = 2 + METADATA_PADDING.len() + 1 + 2;
+
+/// FlvWriter: Encoder that writes the header once and then the tags as they come, the data size,
+/// the timestamp split and the previous tag size of every tag are computed from the payload
+pub struct FlvWriter<W: Write> {
+    stream: W,
+
+    /// Reused between tags to measure the payload before writing the tag header
+    buffer: Vec<u8>,
+
+    /// Bytes written so far, it is also the offset of the next tag
+    position: u64,
+
+    /// Highest timestamp written so far
+    last_timestamp: FlvTimestamp,
+
+    /// `onMetaData` reserved by `write_metadata`, patched by `finish`
+    metadata: Option<MetadataSlot>,
+}
+
+struct MetadataSlot {
+    /// Offset of the tag in the underlying stream (not in the FLV file)
+    stream_offset: u64,
+
+    data_size: usize,
+
+    /// Props of the `onMetaData` ECMA array, without the padding props added by the writer
+    props: Vec<Amf0DataObjectProp>,
+
+    max_keyframes: usize,
+
+    /// (timestamp, offset) of every keyframe written after the metadata
+    keyframes: Vec<(FlvTimestamp, u64)>,
+}
+
+impl<W: Write> FlvWriter<W> {
+    /// Encodes the header and the `PreviousTagSize0` (always 0)
+    pub fn new(mut stream: W, header: &FlvHeader) -> Result<Self, FlvError> {
+        header.encode(&mut stream)?;
+        stream.write_u32::<BigEndian>(0)?;
+
+        Ok(Self {
+            stream,
+            buffer: Vec::new(),
+            position: header.data_offset as u64 + 4,
+            last_timestamp: FlvTimestamp::ZERO,
+            metadata: None,
+        })
+    }
+
+    /// Writes the tag, its data size and previous tag size are computed from its data
+    pub fn write_tag(&mut self, tag: &FlvTag) -> Result<(), FlvError> {
+        self.write_data_with(
+            &tag.data,
+            tag.encryption.as_ref(),
+            tag.timestamp,
+            tag.stream_id,
+        )
+    }
+
+    /// Writes a tag with the given payload
+    pub fn write_data(
+        &mut self,
+        data: &FlvTagData,
+        timestamp: impl Into<FlvTimestamp>,
+    ) -> Result<(), FlvError> {
+        self.write_data_with(data, None, timestamp.into(), 0)
+    }
+
+    pub fn write_video(
+        &mut self,
+        video: FlvVideoData,
+        timestamp: impl Into<FlvTimestamp>,
+    ) -> Result<(), FlvError> {
+        self.write_data(&FlvTagData::Video(video), timestamp)
+    }
+
+    pub fn write_audio(
+        &mut self,
+        audio: FlvAudioTag,
+        timestamp: impl Into<FlvTimestamp>,
+    ) -> Result<(), FlvError> {
+        self.write_data(&FlvTagData::Audio(audio), timestamp)
+    }
+
+    pub fn write_script(
+        &mut self,
+        script: FlvScriptTag,
+        timestamp: impl Into<FlvTimestamp>,
+    ) -> Result<(), FlvError> {
+        self.write_data(&FlvTagData::Script(script), timestamp)
+    }
+
+    fn write_data_with(
+        &mut self,
+        data: &FlvTagData,
+        encryption: Option<&EncryptionTagHeader>,
+        timestamp: FlvTimestamp,
+        stream_id: u32,
+    ) -> Result<(), FlvError> {
+        let written = encode_tag(
+            &mut self.stream,
+            &mut self.buffer,
+            data,
+            encryption,
+            timestamp,
+            stream_id,
+        )?;
+
+        if let Some(metadata) = &mut self.metadata
+            && metadata.keyframes.len() < metadata.max_keyframes
+            && is_keyframe(data)
+        {
+            metadata.keyframes.push((timestamp, self.position));
+        }
+
+        self.position += written as u64;
+        self.last_timestamp = self.last_timestamp.max(timestamp);
+
+        Ok(())
+    }
+
+    /// Offset of the next tag from the start of the file
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    pub fn flush(&mut self) -> Result<(), FlvError> {
+        self.stream.flush()?;
+
+        Ok(())
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.stream
+    }
+
+    pub fn into_inner(self) -> W {
+        self.stream
+    }
+}
+
+impl<W: Write + Seek> FlvWriter<W> {
+    /// Writes an `onMetaData` script tag with the given props and with space reserved for
+    /// `duration`, `filesize`, `lasttimestamp` and a `keyframes` index of up to `max_keyframes`
+    /// entries, the final values are written by `finish`
+    ///
+    /// It should be the first tag of the file, keyframes past `max_keyframes` are not indexed
+    pub fn write_metadata(
+        &mut self,
+        props: Vec<Amf0DataObjectProp>,
+        max_keyframes: usize,
+    ) -> Result<(), FlvError> {
+        let mut props = props;
+
+        // reserve the worst case, every value is replaced by one of the same size
+        let zero = FlvTimestamp::ZERO;
+
+        set_metadata_values(&mut props, zero, 0, zero, &vec![(zero, 0); max_keyframes])?;
+
+        // the padding props are only appended here, a prop of `props` with the same key is kept
+        let mut reserved = props.clone();
+        reserved.push(padding_prop(0)?);
+
+        let script = FlvScriptTag::from_props(FLV_SCRIPT_ON_METADATA.to_string(), reserved)?;
+
+        let stream_offset = self.stream.stream_position()?;
+        let position = self.position;
+
+        self.write_data(&FlvTagData::Script(script), 0)?;
+
+        self.metadata = Some(MetadataSlot {
+            stream_offset,
+            data_size: (self.position - position) as usize - 15,
+            props,
+            max_keyframes,
+            keyframes: Vec::new(),
+        });
+
+        Ok(())
+    }
+
+    /// Rewrites the `onMetaData` reserved by `write_metadata` with the final values, flushes and
+    /// returns the stream positioned at the end of the file
+    pub fn finish(mut self) -> Result<W, FlvError> {
+        if let Some(metadata) = self.metadata.take() {
+            let mut props = metadata.props;
+
+            set_metadata_values(
+                &mut props,
+                self.last_timestamp,
+                self.position,
+                self.last_timestamp,
+                &metadata.keyframes,
+            )?;
+
+            let name = FLV_SCRIPT_ON_METADATA.to_string();
+            let used = FlvScriptTag::from_props(name.clone(), props.clone())?.encoded_len();
+
+            let mut remaining = metadata
+                .data_size
+                .checked_sub(used)
+                .filter(|remaining| *remaining >= METADATA_PADDING_OVERHEAD)
+                .ok_or(FlvError::TagTooLarge(used))?;
+
+            // AMF0 strings are limited to u16::MAX, so the padding may need several props
+            while remaining > 0 {
+                let len = if remaining - METADATA_PADDING_OVERHEAD <= u16::MAX as usize {
+                    remaining - METADATA_PADDING_OVERHEAD
+                } else {
+                    // leave enough room for the next padding prop
+                    (u16::MAX as usize).min(remaining - 2 * METADATA_PADDING_OVERHEAD)
+                };
+
+                props.push(padding_prop(len)?);
+
+                remaining -= len + METADATA_PADDING_OVERHEAD;
+            }
+
+            let end = self.stream.stream_position()?;
+
+            self.stream.seek(SeekFrom::Start(metadata.stream_offset))?;
+            encode_tag(
+                &mut self.stream,
+                &mut self.buffer,
+                &FlvTagData::Script(FlvScriptTag::from_props(name, props)?),
+                None,
+                FlvTimestamp::ZERO,
+                0,
+            )?;
+            self.stream.seek(SeekFrom::Start(end))?;
+        }
+
+        self.stream.flush()?;
+
+        Ok(self.stream)
+    }
+}
+
+fn is_keyframe(data: &FlvTagData) -> bool {
+    let FlvTagData::Video(video) = data else {
+        return false;
+    };
+
+    if video.frame_type != FrameType::Keyframe {
+        return false;
+    }
+
+    // the sequence header is not a frame to seek to
+    !video.is_sequence_header()
+}
+
+/// Prop that fills `len` bytes of its string and `METADATA_PADDING_OVERHEAD` more
+fn padding_prop(len: usize) -> Result<Amf0DataObjectProp, FlvError> {
+    Ok(Amf0DataObjectProp {
+        name: Amf0Key::new(METADATA_PADDING.to_string())?,
+        value: Amf0Value::String(Amf0String::new(" ".repeat(len))?),
+    })
+}
+
+/// Sets the values known at the end of the recording, durations are in seconds
+fn set_metadata_values(
+    props: &mut Vec<Amf0DataObjectProp>,
+    duration: FlvTimestamp,
+    filesize: u64,
+    lasttimestamp: FlvTimestamp,
+    keyframes: &[(FlvTimestamp, u64)],
+) -> Result<(), FlvError> {
+    let seconds = |timestamp: FlvTimestamp| {
+        Amf0Value::Number(Amf0Number::new(timestamp.to_duration().as_secs_f64()))
+    };
+
+    set_metadata_prop(props, "duration", seconds(duration))?;
+    set_metadata_prop(
+        props,
+        "filesize",
+        Amf0Value::Number(Amf0Number::new(filesize as f64)),
+    )?;
+    set_metadata_prop(props, "lasttimestamp", seconds(lasttimestamp))?;
+
+    let filepositions = keyframes
+        .iter()
+        .map(|(_, offset)| Amf0Value::Number(Amf0Number::new(*offset as f64)))
+        .collect();
+
+    let times = keyframes
+        .iter()
+        .map(|(timestamp, _)| seconds(*timestamp))
+        .collect();
+
+    let index = Amf0Object::new(vec![
+        Amf0DataObjectProp {
+            name: Amf0Key::new("filepositions".to_string())?,
+            value: Amf0Value::StrictArray(Amf0StrictArray::new(filepositions)),
+        },
+        Amf0DataObjectProp {
+            name: Amf0Key::new("times".to_string())?,
+            value: Amf0Value::StrictArray(Amf0StrictArray::new(times)),
+        },
+    ]);
+
+    set_metadata_prop(props, "keyframes", Amf0Value::Object(index))
+}
+
+/// Replaces the value of the prop, or appends it if there isn't one
+fn set_metadata_prop(
+    props: &mut Vec<Amf0DataObjectProp>,
+    name: &str,
+    value: Amf0Value,
+) -> Result<(), FlvError> {
+    match props.iter_mut().find(|prop| prop.name.key == name) {
+        Some(prop) => prop.value = value,
+        None => props.push(Amf0DataObjectProp {
+            name: Amf0Key::new(name.to_string())?,
+            value,
+        }),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,84 @@
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use rflv::amf0::{from_reader, to_writer};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Codec {
+    Avc,
+    Other { id: u8 },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Size {
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Stream {
+    title: String,
+    duration: f64,
+    has_audio: bool,
+    size: Size,
+    encoder: Option<String>,
+    codecs: Vec<Codec>,
+    #[serde(with = "rflv::amf0::date")]
+    creation_date: SystemTime,
+}
+
+/// Encodes `value` with `to_writer` and decodes it back with `from_reader`, the first byte is the
+/// AMF0 marker
+fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(value: &T) -> (u8, T) {
+    let mut buf = Vec::new();
+
+    to_writer(&mut buf, value).unwrap();
+
+    let mut rest = &buf[..];
+    let decoded = from_reader(&mut rest).unwrap();
+
+    assert!(rest.is_empty(), "{} bytes left", rest.len());
+
+    (buf[0], decoded)
+}
+
+#[test]
+fn struct_round_trip() {
+    let stream = Stream {
+        title: "rflv".to_string(),
+        duration: 12.5,
+        has_audio: true,
+        size: Size {
+            width: 1280,
+            height: 720,
+        },
+        encoder: None,
+        codecs: vec![Codec::Avc, Codec::Other { id: 7 }],
+        creation_date: UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+    };
+
+    let (marker, decoded) = round_trip(&stream);
+
+    // structs are Objects
+    assert_eq!(marker, 0x03);
+    assert_eq!(decoded, stream);
+}
+
+#[test]
+fn map_round_trip() {
+    let map = BTreeMap::from([
+        ("audiodatarate".to_string(), 128.0),
+        ("framerate".to_string(), 29.97),
+        ("videodatarate".to_string(), 2500.0),
+    ]);
+
+    let (marker, decoded) = round_trip(&map);
+
+    // maps are ECMA arrays
+    assert_eq!(marker, 0x08);
+    assert_eq!(decoded, map);
+}
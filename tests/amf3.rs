@@ -0,0 +1,108 @@
+use rflv::v1::amf3::{Amf3Error, Amf3Marker, Amf3Traits, Amf3Value};
+
+/// Dense array of two `Point` objects, the second referencing the traits of the first, the class
+/// name again as a string reference and a reference to the first object
+const REFERENCES: &[u8] = &[
+    0x09, 0x09, 0x01, // array, 4 dense values, no associative part
+    0x0A, 0x23, 0x0B, b'P', b'o', b'i', b'n', b't', 0x03, b'x', 0x03, b'y', // inline traits
+    0x04, 0x01, 0x04, 0x02, // x = 1, y = 2
+    0x0A, 0x01, // traits reference 0
+    0x04, 0x03, 0x04, 0x04, // x = 3, y = 4
+    0x06, 0x00, // string reference 0
+    0x0A, 0x02, // object reference 1
+];
+
+/// Sealed values of a `Point`
+fn point(value: &Amf3Value, traits: &Amf3Traits) -> [i32; 2] {
+    let Amf3Value::Object(object) = value else {
+        panic!("not an object: {value:?}");
+    };
+
+    assert_eq!(&object.traits, traits);
+
+    let [Amf3Value::Integer(x), Amf3Value::Integer(y)] = object.sealed[..] else {
+        panic!("not a point: {object:?}");
+    };
+
+    [x, y]
+}
+
+/// Two `Point` objects that both send their traits inline, the second time with the class name
+/// and the member names as string references
+const REPEATED_TRAITS: &[u8] = &[
+    0x09, 0x05, 0x01, // array, 2 dense values, no associative part
+    0x0A, 0x23, 0x0B, b'P', b'o', b'i', b'n', b't', 0x03, b'x', 0x03, b'y', // inline traits
+    0x04, 0x01, 0x04, 0x02, // x = 1, y = 2
+    0x0A, 0x23, 0x00, 0x02, 0x04, // inline traits again
+    0x04, 0x03, 0x04, 0x04, // x = 3, y = 4
+];
+
+#[test]
+fn decode_references() {
+    let value = Amf3Value::decode(&mut &REFERENCES[..]).unwrap();
+
+    let Amf3Value::Array(array) = &value else {
+        panic!("not an array: {value:?}");
+    };
+
+    assert!(array.assoc.is_empty());
+    assert_eq!(array.dense.len(), 4);
+
+    let traits = Amf3Traits {
+        class_name: "Point".to_string(),
+        sealed: vec!["x".to_string(), "y".to_string()],
+        ..Default::default()
+    };
+
+    assert_eq!(point(&array.dense[0], &traits), [1, 2]);
+    assert_eq!(point(&array.dense[1], &traits), [3, 4]);
+
+    assert!(matches!(&array.dense[2], Amf3Value::String(s) if s == "Point"));
+
+    // the array is object 0, the first point object 1
+    assert!(matches!(
+        array.dense[3],
+        Amf3Value::Reference(reference)
+            if reference.marker == Amf3Marker::Object && reference.index == 1
+    ));
+
+    // the encoder writes the same references
+    let mut buf = Vec::new();
+    value.encode(&mut buf).unwrap();
+
+    assert_eq!(buf, REFERENCES);
+}
+
+#[test]
+fn invalid_references() {
+    // string, traits and object references before anything was sent
+    for input in [&[0x06, 0x00][..], &[0x0A, 0x01], &[0x0A, 0x00]] {
+        let e = Amf3Value::decode(&mut &input[..]).unwrap_err();
+
+        assert!(matches!(e, Amf3Error::InvalidReference(0)), "{e}");
+    }
+}
+
+#[test]
+fn repeated_inline_traits() {
+    let value = Amf3Value::decode(&mut &REPEATED_TRAITS[..]).unwrap();
+
+    let Amf3Value::Array(array) = &value else {
+        panic!("not an array: {value:?}");
+    };
+
+    let traits = Amf3Traits {
+        class_name: "Point".to_string(),
+        sealed: vec!["x".to_string(), "y".to_string()],
+        ..Default::default()
+    };
+
+    assert_eq!(point(&array.dense[0], &traits), [1, 2]);
+    assert_eq!(point(&array.dense[1], &traits), [3, 4]);
+
+    // the second traits are not turned into a reference to the first
+    let mut buf = Vec::new();
+    value.encode(&mut buf).unwrap();
+
+    assert_eq!(buf, REPEATED_TRAITS);
+}
@@ -0,0 +1,153 @@
+use futures_util::{SinkExt, StreamExt};
+use rflv::{
+    async_io::{FlvSink, FlvStream},
+    error::FlvError,
+    v1::{
+        header::FlvHeader,
+        limits::DecodeLimits,
+        tag::{FlvTag, FlvTagRefIter},
+    },
+};
+
+const SAMPLE: &[u8] = include_bytes!("../example/file.flv");
+
+/// Header (9) + PreviousTagSize0 (4)
+const FIRST_TAG: usize = 13;
+
+fn sample_tags() -> Vec<FlvTag> {
+    FlvTagRefIter::new(&SAMPLE[FIRST_TAG..])
+        .map(|tag| tag.unwrap().to_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn sink_then_stream() {
+    let header = FlvHeader::decode(&mut &SAMPLE[..]).unwrap();
+    let tags = sample_tags();
+
+    let mut sink = FlvSink::new(Vec::new(), &header).unwrap();
+
+    for tag in &tags {
+        sink.send(tag.clone()).await.unwrap();
+    }
+
+    sink.close().await.unwrap();
+
+    let encoded = sink.into_inner();
+    assert_eq!(encoded, SAMPLE);
+
+    let mut stream = FlvStream::new(&encoded[..]);
+    let mut decoded = Vec::new();
+
+    while let Some(tag) = stream.next().await {
+        tag.unwrap().encode(&mut decoded).unwrap();
+    }
+
+    assert_eq!(stream.header().unwrap().data_offset, header.data_offset);
+    assert_eq!(decoded, SAMPLE[FIRST_TAG..]);
+}
+
+#[tokio::test]
+async fn truncated_stream() {
+    let mut stream = FlvStream::new(&SAMPLE[..SAMPLE.len() - 1]);
+    let mut last = None;
+
+    while let Some(tag) = stream.next().await {
+        last = Some(tag);
+    }
+
+    let e = last.unwrap().unwrap_err();
+    assert!(matches!(e.root(), FlvError::Truncated { .. }), "{e}");
+}
+
+#[tokio::test]
+async fn error_after_tags_of_the_last_chunk() {
+    let first_tag = FlvTagRefIter::new(&SAMPLE[FIRST_TAG..])
+        .next()
+        .unwrap()
+        .unwrap();
+    let first_tag_len = first_tag.to_owned().encoded_len();
+
+    // a video tag whose DataSize can't hold the FrameType/CodecId byte, all in one chunk
+    let mut input = SAMPLE[..FIRST_TAG + first_tag_len].to_vec();
+    input.extend_from_slice(&[9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 11]);
+
+    let mut stream = FlvStream::new(&input[..]);
+
+    assert!(stream.next().await.unwrap().is_ok());
+
+    let e = stream.next().await.unwrap().unwrap_err();
+    assert!(matches!(e.root(), FlvError::DataSizeTooSmall(0)), "{e}");
+    assert_eq!(e.tag_index(), Some(1));
+
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn limits_are_checked_before_reading() {
+    let limits = DecodeLimits {
+        max_tag_size: 16,
+        ..DecodeLimits::default()
+    };
+
+    // only the tag header is available, the data would be read if the size was not checked
+    let tag_header = [9, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0];
+    let e = FlvTag::decode_async_with_limits(&mut &tag_header[..], &limits)
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(e.root(), FlvError::LimitExceeded("max_tag_size")),
+        "{e}"
+    );
+
+    let limits = DecodeLimits {
+        max_total_alloc: 1024,
+        ..DecodeLimits::default()
+    };
+
+    // data_offset of 4 GiB
+    let header = [b'F', b'L', b'V', 1, 1, 0xFF, 0xFF, 0xFF, 0xFF];
+    let e = FlvHeader::decode_async_with_limits(&mut &header[..], &limits)
+        .await
+        .unwrap_err();
+
+    assert!(
+        matches!(e.root(), FlvError::LimitExceeded("max_total_alloc")),
+        "{e}"
+    );
+}
+
+#[tokio::test]
+async fn truncated_tag() {
+    let mut input = &SAMPLE[FIRST_TAG..];
+    let first_tag = FlvTag::decode(&mut input).unwrap();
+    let len = first_tag.encoded_len();
+
+    for end in [5, len - 1] {
+        let e = FlvTag::decode_async(&mut &SAMPLE[FIRST_TAG..FIRST_TAG + end])
+            .await
+            .unwrap_err();
+
+        assert!(matches!(e.root(), FlvError::Truncated { .. }), "{e}");
+    }
+}
+
+#[tokio::test]
+async fn truncated_header() {
+    for end in 0..FIRST_TAG {
+        if end < 9 {
+            let e = FlvHeader::decode_async(&mut &SAMPLE[..end])
+                .await
+                .unwrap_err();
+            assert!(matches!(e.root(), FlvError::Truncated { .. }), "{e}");
+        }
+
+        let e = FlvStream::new(&SAMPLE[..end])
+            .next()
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(matches!(e.root(), FlvError::Truncated { .. }), "{e}");
+    }
+}
@@ -0,0 +1,40 @@
+use bytes::Bytes;
+use rflv::v1::tag::FlvTag;
+
+const SAMPLE: &[u8] = include_bytes!("../example/file.flv");
+const FIRST_TAG: usize = 13;
+
+#[test]
+fn decode_bytes_shares_the_input() {
+    let input = Bytes::from_static(SAMPLE);
+    let range = input.as_ptr_range();
+
+    let mut offset = FIRST_TAG;
+    let mut payloads = 0;
+
+    while offset < input.len() {
+        let tag = FlvTag::decode_bytes(&input.slice(offset..)).unwrap();
+        let copied = FlvTag::decode(&mut &SAMPLE[offset..]).unwrap();
+
+        assert_eq!(tag.timestamp, copied.timestamp);
+        assert_eq!(tag.encoded_len(), copied.encoded_len());
+
+        if let Some(payload) = tag.data.payload() {
+            // a slice of the input, not a copy
+            let data = payload.as_ptr_range();
+            assert!(range.start <= data.start && data.end <= range.end);
+
+            assert_eq!(Some(payload), copied.data.payload());
+            payloads += 1;
+        }
+
+        let mut encoded = Vec::new();
+        tag.encode(&mut encoded).unwrap();
+
+        assert_eq!(encoded, SAMPLE[offset..offset + tag.encoded_len()]);
+
+        offset += tag.encoded_len();
+    }
+
+    assert!(payloads > 0);
+}
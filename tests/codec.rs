@@ -0,0 +1,117 @@
+use bytes::BytesMut;
+use futures_util::StreamExt;
+use rflv::{
+    codec::FlvCodec,
+    error::FlvError,
+    v1::{
+        header::FlvHeader,
+        script::{Amf0DataObjectProp, Amf0Key, Amf0Object, Amf0Value, FlvScriptTag},
+        tag::{FlvTag, FlvTagRefIter},
+    },
+};
+use tokio_util::codec::{Decoder, Encoder, FramedRead};
+
+const SAMPLE: &[u8] = include_bytes!("../example/file.flv");
+
+/// Header (9) + PreviousTagSize0 (4)
+const FIRST_TAG: usize = 13;
+
+#[test]
+fn decode_split_buffers() {
+    let expected = FlvTagRefIter::new(&SAMPLE[FIRST_TAG..]).count();
+
+    // chunk sizes that split both the header and the tag headers
+    for chunk_size in [1, 7, 100, 4096] {
+        let mut codec = FlvCodec::new();
+        let mut buf = BytesMut::new();
+        let mut encoded = Vec::new();
+        let mut tags = 0;
+
+        for chunk in SAMPLE.chunks(chunk_size) {
+            buf.extend_from_slice(chunk);
+
+            while let Some(tag) = codec.decode(&mut buf).unwrap() {
+                tag.encode(&mut encoded).unwrap();
+                tags += 1;
+            }
+        }
+
+        assert!(codec.decode_eof(&mut buf).unwrap().is_none());
+        assert!(codec.header().is_some());
+        assert_eq!(tags, expected, "chunks of {chunk_size}");
+        assert_eq!(encoded, SAMPLE[FIRST_TAG..], "chunks of {chunk_size}");
+    }
+}
+
+#[test]
+fn encode_with_header() {
+    let header = FlvHeader::decode(&mut &SAMPLE[..]).unwrap();
+    let mut codec = FlvCodec::with_header(header);
+    let mut buf = BytesMut::new();
+
+    for tag in FlvTagRefIter::new(&SAMPLE[FIRST_TAG..]) {
+        codec.encode(tag.unwrap().to_owned(), &mut buf).unwrap();
+    }
+
+    assert_eq!(buf, SAMPLE);
+}
+
+#[test]
+fn failed_encode_keeps_the_header() {
+    let header = FlvHeader::decode(&mut &SAMPLE[..]).unwrap();
+    let mut codec = FlvCodec::with_header(header);
+    let mut buf = BytesMut::new();
+
+    // a key longer than an AMF0 string can be
+    let prop = Amf0DataObjectProp {
+        name: Amf0Key {
+            key: "k".repeat(u16::MAX as usize + 1),
+        },
+        value: Amf0Value::Null,
+    };
+    let values = vec![Amf0Value::Object(Amf0Object::new(vec![prop]))];
+    let script = FlvScriptTag::new("onMetaData".to_string(), values).unwrap();
+
+    assert!(
+        codec
+            .encode(FlvTag::new_script(script, 0), &mut buf)
+            .is_err()
+    );
+    assert!(buf.is_empty());
+
+    for tag in FlvTagRefIter::new(&SAMPLE[FIRST_TAG..]) {
+        codec.encode(tag.unwrap().to_owned(), &mut buf).unwrap();
+    }
+
+    assert_eq!(buf, SAMPLE);
+}
+
+#[tokio::test]
+async fn truncated_stream() {
+    let mut tags = FramedRead::new(&SAMPLE[..SAMPLE.len() - 1], FlvCodec::new());
+    let mut last = None;
+
+    while let Some(tag) = tags.next().await {
+        last = Some(tag);
+    }
+
+    let e = last.unwrap().unwrap_err();
+
+    assert!(matches!(e.root(), FlvError::Truncated { .. }), "{e}");
+    assert!(e.tag_index().is_some());
+
+    // a stream that ends in the header
+    let mut tags = FramedRead::new(&SAMPLE[..5], FlvCodec::new());
+    let e = tags.next().await.unwrap().unwrap_err();
+
+    assert!(
+        matches!(
+            e.root(),
+            FlvError::Truncated {
+                expected: 13,
+                got: 5
+            }
+        ),
+        "{e}"
+    );
+}
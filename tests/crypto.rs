@@ -0,0 +1,64 @@
+use rflv::{
+    crypto::{decrypt_file, encrypt_file, encrypt_tag},
+    file::FlvFile,
+    v1::{
+        Payload,
+        tag::{FlvTag, FlvTagData},
+    },
+};
+
+const SAMPLE: &[u8] = include_bytes!("../example/file.flv");
+
+const KEY: [u8; 16] = [0x11; 16];
+
+/// `Payload` is `Vec<u8>` or `bytes::Bytes` depending on the features
+#[allow(clippy::useless_conversion)]
+fn payload(data: &[u8]) -> Payload {
+    data.to_vec().into()
+}
+
+#[test]
+fn encrypt_then_decrypt_file() {
+    let file = FlvFile::decode(&mut &SAMPLE[..]).unwrap();
+
+    let mut counter = 0_u8;
+    let encrypted = encrypt_file(file, &KEY, |_| {
+        counter += 1;
+        [counter; 16]
+    })
+    .unwrap();
+
+    assert!(counter > 0);
+
+    for tag in &encrypted.tags {
+        let encryptable = matches!(tag.data, FlvTagData::Audio(_) | FlvTagData::Video(_));
+        assert_eq!(tag.is_filtered(), encryptable);
+    }
+
+    // the filtered file is decoded from its bytes, as a player would
+    let mut encoded = Vec::new();
+    encrypted.encode(&mut encoded).unwrap();
+    assert_ne!(encoded, SAMPLE);
+
+    let encrypted = FlvFile::decode(&mut &encoded[..]).unwrap();
+    let decrypted = decrypt_file(encrypted, &KEY).unwrap();
+
+    let mut encoded = Vec::new();
+    decrypted.encode(&mut encoded).unwrap();
+    assert_eq!(encoded, SAMPLE);
+}
+
+#[test]
+fn unknown_tags_are_left_clear() {
+    let tag = FlvTag {
+        timestamp: 0.into(),
+        stream_id: 0,
+        encryption: None,
+        data: FlvTagData::Unknown(15, payload(&[1, 2, 3])),
+    };
+
+    let tag = encrypt_tag(tag, &KEY, [0; 16]).unwrap();
+
+    assert!(!tag.is_filtered());
+    assert!(matches!(&tag.data, FlvTagData::Unknown(15, data) if data[..] == [1, 2, 3]));
+}
@@ -0,0 +1,102 @@
+use rflv::{
+    demux::{FlvDemuxer, FlvEvent},
+    v1::tag::FlvTagRefIter,
+};
+
+const SAMPLE: &[u8] = include_bytes!("../example/file.flv");
+
+/// Header (9) + PreviousTagSize0 (4)
+const FIRST_TAG: usize = 13;
+
+#[test]
+fn byte_at_a_time() {
+    let mut demuxer = FlvDemuxer::new();
+    let mut events = Vec::new();
+
+    for byte in SAMPLE {
+        events.extend(demuxer.push(&[*byte]).unwrap());
+    }
+
+    events.extend(demuxer.finish().unwrap());
+
+    assert!(demuxer.is_at_tag_boundary());
+    assert_eq!(demuxer.offset(), SAMPLE.len() as u64);
+
+    let mut events = events.into_iter();
+    let Some(FlvEvent::Header(header)) = events.next() else {
+        panic!("the first event is not the header");
+    };
+
+    let mut encoded = Vec::new();
+    header.encode(&mut encoded).unwrap();
+    encoded.extend_from_slice(&[0; 4]);
+
+    let expected = FlvTagRefIter::new(&SAMPLE[FIRST_TAG..]).count();
+    let mut tags = 0;
+
+    for event in events {
+        let FlvEvent::Tag(tag) = event else {
+            panic!("unexpected event {event:?}");
+        };
+
+        tag.encode(&mut encoded).unwrap();
+        tags += 1;
+    }
+
+    assert_eq!(tags, expected);
+    assert_eq!(encoded, SAMPLE);
+}
+
+#[test]
+fn partial_tag_at_finish() {
+    let mut demuxer = FlvDemuxer::new();
+
+    let events = demuxer.push(&SAMPLE[..SAMPLE.len() - 1]).unwrap();
+
+    assert!(!events.is_empty());
+    assert!(!demuxer.is_at_tag_boundary());
+    assert!(demuxer.finish().is_err());
+}
+
+#[test]
+fn recovery_skips_corrupt_tag() {
+    let mut offsets = vec![FIRST_TAG];
+    let mut iter = FlvTagRefIter::new(&SAMPLE[FIRST_TAG..]);
+
+    while iter.next().is_some() {
+        offsets.push(SAMPLE.len() - iter.remaining().len());
+    }
+
+    let tags = offsets.len() - 1;
+    let corrupt = offsets[2]..offsets[3];
+
+    // the PreviousTagSize of the third tag doesn't match its size
+    let mut input = SAMPLE.to_vec();
+    input[corrupt.end - 1] ^= 0xFF;
+
+    let mut demuxer = FlvDemuxer::with_recovery();
+    let mut events = Vec::new();
+
+    for chunk in input.chunks(64) {
+        events.extend(demuxer.push(chunk).unwrap());
+    }
+
+    events.extend(demuxer.finish().unwrap());
+
+    let skipped = events
+        .iter()
+        .filter_map(|event| match event {
+            FlvEvent::Skipped(range) => Some(range.clone()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+
+    let decoded = events
+        .iter()
+        .filter(|event| matches!(event, FlvEvent::Tag(_)))
+        .count();
+
+    assert_eq!(skipped.len(), 1);
+    assert_eq!(skipped[0], corrupt.start as u64..corrupt.end as u64);
+    assert_eq!(decoded, tags - 1);
+}
@@ -0,0 +1,349 @@
+use rflv::{
+    reader::FlvReader,
+    v1::{
+        Payload,
+        amf3::{Amf3Array, Amf3Object, Amf3Value},
+        audio::{
+            AacAudioData, AudioData, FlvAudioTag, SoundFormat, SoundRate, SoundSize, SoundType,
+        },
+        encryption::{EncryptionTagHeader, FilterParams},
+        header::{FlvHeader, HeaderFlags},
+        metadata::OnMetaData,
+        script::{
+            Amf0Bool, Amf0DataObjectProp, Amf0Date, Amf0EcmaArray, Amf0Key, Amf0LongString,
+            Amf0Number, Amf0Object, Amf0Reference, Amf0StrictArray, Amf0String, Amf0TypedObject,
+            Amf0Value, Amf0XmlDocument, FlvScriptTag,
+        },
+        tag::{FlvTag, FlvTagData, FlvTagRef, FlvTagType},
+        video::{AvcVideoPacket, CodecId, FlvVideoData, FrameType, VideoData},
+    },
+    writer::FlvWriter,
+};
+
+const SAMPLE: &[u8] = include_bytes!("../example/file.flv");
+
+/// Encodes with `encode` and checks that `encoded_len` is the number of bytes written
+macro_rules! assert_encoded_len {
+    ($value:expr) => {{
+        let value = &$value;
+        let mut buf = Vec::new();
+
+        value.encode(&mut buf).unwrap();
+
+        assert_eq!(value.encoded_len(), buf.len(), "{}", stringify!($value));
+    }};
+}
+
+/// `Payload` is `Vec<u8>` or `bytes::Bytes` depending on the features
+#[allow(clippy::useless_conversion)]
+fn payload(data: &[u8]) -> Payload {
+    data.to_vec().into()
+}
+
+fn key(key: &str) -> Amf0Key {
+    Amf0Key::new(key.to_string()).unwrap()
+}
+
+fn string(content: &str) -> Amf0String {
+    Amf0String::new(content.to_string()).unwrap()
+}
+
+fn prop(name: &str, value: Amf0Value) -> Amf0DataObjectProp {
+    Amf0DataObjectProp {
+        name: key(name),
+        value,
+    }
+}
+
+fn avc_video(packet: AvcVideoPacket) -> FlvVideoData {
+    FlvVideoData {
+        frame_type: FrameType::Keyframe,
+        codec: CodecId::Avc,
+        video_data: VideoData::Avc(packet),
+    }
+}
+
+fn aac_audio(packet_type: u8, data: &[u8]) -> FlvAudioTag {
+    FlvAudioTag {
+        sound_format: SoundFormat::Aac,
+        sound_rate: SoundRate::Khz44,
+        sound_size: SoundSize::Snd16Bit,
+        sound_type: SoundType::Stereo,
+        data: AudioData::Aac(AacAudioData {
+            packet_type,
+            data: payload(data),
+        }),
+    }
+}
+
+fn metadata() -> FlvScriptTag {
+    let object = Amf0Object::new(vec![
+        prop("codec", Amf0Value::String(string("avc1"))),
+        prop("level", Amf0Value::Number(Amf0Number::new(3.1))),
+    ]);
+
+    let array = Amf0StrictArray::new(vec![
+        Amf0Value::Number(Amf0Number::new(0.0)),
+        Amf0Value::Bool(Amf0Bool::new(true)),
+        Amf0Value::String(string("")),
+        Amf0Value::Null,
+        Amf0Value::Undefined,
+    ]);
+
+    let typed = Amf0TypedObject::new(
+        "Point".to_string(),
+        vec![prop("x", Amf0Value::Number(Amf0Number::new(1.0)))],
+    )
+    .unwrap();
+
+    let nested = Amf0EcmaArray::new(vec![
+        prop("created", Amf0Value::Date(Amf0Date::new(1.7e12))),
+        prop("point", Amf0Value::TypedObject(typed)),
+        prop("same_point", Amf0Value::Reference(Amf0Reference::new(1))),
+    ]);
+
+    FlvScriptTag::from_props(
+        "onMetaData".to_string(),
+        vec![
+            prop("duration", Amf0Value::Number(Amf0Number::new(12.5))),
+            prop("stereo", Amf0Value::Bool(Amf0Bool::new(false))),
+            prop("encoder", Amf0Value::String(string("rflv"))),
+            prop("video", Amf0Value::Object(object)),
+            prop("times", Amf0Value::StrictArray(array)),
+            prop("nested", Amf0Value::EcmaArray(nested)),
+            prop(
+                "comment",
+                Amf0Value::LongString(Amf0LongString::new("a".repeat(70_000)).unwrap()),
+            ),
+            prop(
+                "xml",
+                Amf0Value::XmlDocument(Amf0XmlDocument::new("<a/>".to_string()).unwrap()),
+            ),
+            prop("missing", Amf0Value::Unsupported),
+            prop("amf3", Amf0Value::Amf3(amf3())),
+        ],
+    )
+    .unwrap()
+}
+
+fn amf3() -> Amf3Value {
+    let point = Amf3Object::new(vec![
+        ("x".to_string(), Amf3Value::Integer(-1)),
+        ("y".to_string(), Amf3Value::Integer(1 << 28)),
+    ]);
+
+    Amf3Value::Array(Amf3Array {
+        assoc: vec![("name".to_string(), Amf3Value::String("name".to_string()))],
+        dense: vec![
+            Amf3Value::Object(point.clone()),
+            Amf3Value::Object(point),
+            Amf3Value::ByteArray(vec![1, 2, 3]),
+        ],
+    })
+}
+
+#[test]
+fn header() {
+    assert_encoded_len!(FlvHeader::new(HeaderFlags::AUDIO | HeaderFlags::VIDEO));
+    assert_encoded_len!(FlvHeader::new(HeaderFlags::VIDEO).with_extra(vec![1, 2, 3]));
+}
+
+#[test]
+fn video() {
+    let sequence_header = AvcVideoPacket::new_sequence_header(vec![1, 2, 3, 4]);
+    let nalu = AvcVideoPacket::new_nalu(vec![0; 100], -40);
+
+    assert_encoded_len!(sequence_header);
+    assert_encoded_len!(nalu);
+    assert_encoded_len!(AvcVideoPacket::eos());
+
+    assert_encoded_len!(VideoData::Avc(nalu.clone()));
+    assert_encoded_len!(VideoData::Other(payload(&[7; 10])));
+
+    assert_encoded_len!(avc_video(sequence_header));
+    assert_encoded_len!(avc_video(AvcVideoPacket::eos()));
+    assert_encoded_len!(FlvVideoData {
+        frame_type: FrameType::InterFrame,
+        codec: CodecId::Vp6,
+        video_data: VideoData::Other(payload(&[7; 10])),
+    });
+}
+
+#[test]
+fn audio() {
+    let aac = AacAudioData {
+        packet_type: 1,
+        data: payload(&[0; 20]),
+    };
+
+    assert_encoded_len!(aac);
+    assert_encoded_len!(AudioData::Aac(aac.clone()));
+    assert_encoded_len!(AudioData::Other(payload(&[1; 5])));
+
+    assert_encoded_len!(aac_audio(0, &[0x12, 0x10]));
+    assert_encoded_len!(FlvAudioTag {
+        sound_format: SoundFormat::Mp3,
+        sound_rate: SoundRate::Khz22,
+        sound_size: SoundSize::Snd16Bit,
+        sound_type: SoundType::Mono,
+        data: AudioData::Other(payload(&[1; 5])),
+    });
+}
+
+#[test]
+fn script() {
+    let script = metadata();
+
+    assert_encoded_len!(key("duration"));
+    assert_encoded_len!(string("rflv"));
+    assert_encoded_len!(Amf0Bool::new(true));
+    assert_encoded_len!(Amf0Number::new(1.0));
+    assert_encoded_len!(Amf0Object::new(vec![]));
+    assert_encoded_len!(Amf0StrictArray::new(vec![]));
+    assert_encoded_len!(Amf0EcmaArray::new(script.props().unwrap().to_vec()));
+    assert_encoded_len!(Amf0Reference::new(3));
+    assert_encoded_len!(Amf0Date::new(0.0));
+    assert_encoded_len!(Amf0LongString::new(String::new()).unwrap());
+    assert_encoded_len!(Amf0XmlDocument::new(String::new()).unwrap());
+    assert_encoded_len!(Amf0TypedObject::new(String::new(), vec![]).unwrap());
+    assert_encoded_len!(amf3());
+
+    for prop in script.props().unwrap() {
+        assert_encoded_len!(prop);
+        assert_encoded_len!(prop.value);
+    }
+
+    assert_encoded_len!(script);
+
+    let typed = OnMetaData::try_from(&script).unwrap();
+
+    assert_eq!(typed.duration, Some(12.5));
+    assert!(typed.get_extra("nested").is_some());
+    assert_encoded_len!(FlvScriptTag::try_from(&typed).unwrap());
+}
+
+#[test]
+fn encryption_header() {
+    let iv = [0xAB; 16];
+
+    assert_encoded_len!(EncryptionTagHeader::new(FilterParams::Encryption { iv }));
+    assert_encoded_len!(EncryptionTagHeader::new(
+        FilterParams::SelectiveEncryption { iv: Some(iv) }
+    ));
+    assert_encoded_len!(EncryptionTagHeader::new(
+        FilterParams::SelectiveEncryption { iv: None }
+    ));
+    assert_encoded_len!(EncryptionTagHeader::new(FilterParams::Other {
+        name: "Custom".to_string(),
+        params: payload(&[1, 2, 3]),
+    }));
+}
+
+#[test]
+fn tags() {
+    let mut filtered = FlvTag::new_audio(aac_audio(1, &[9; 32]), 40);
+    filtered.encryption = Some(EncryptionTagHeader::new(FilterParams::Encryption {
+        iv: [1; 16],
+    }));
+
+    // two booleans followed by a stray object end
+    let mut sample_access = FlvScriptTag::new(
+        "|RtmpSampleAccess".to_string(),
+        vec![
+            Amf0Value::Bool(Amf0Bool::new(false)),
+            Amf0Value::Bool(Amf0Bool::new(false)),
+        ],
+    )
+    .unwrap();
+    sample_access.trailing = payload(&[0, 0, 9]);
+
+    let tags = [
+        FlvTag::new_script(metadata(), 0),
+        FlvTag::new_script(sample_access, 0),
+        FlvTag::new_video(avc_video(AvcVideoPacket::new_nalu(vec![0; 64], 0)), 33),
+        FlvTag::new_audio(aac_audio(1, &[0; 16]), 0xFF_FFFF + 1),
+        filtered,
+    ];
+
+    for tag in &tags {
+        assert_encoded_len!(tag);
+        assert_encoded_len!(tag.data);
+
+        let mut buf = Vec::new();
+        tag.encode(&mut buf).unwrap();
+
+        // DataSize and PreviousTagSize are the ones computed from the data
+        let data_size = u32::from_be_bytes([0, buf[1], buf[2], buf[3]]) as usize;
+        let previous_tag_size = u32::from_be_bytes(buf[buf.len() - 4..].try_into().unwrap());
+
+        assert_eq!(data_size, tag.data_size());
+        assert_eq!(previous_tag_size as usize, 11 + tag.data_size());
+
+        let decoded = FlvTag::decode(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.encoded_len(), buf.len());
+
+        let mut encoded = Vec::new();
+        decoded.encode(&mut encoded).unwrap();
+        assert_eq!(encoded, buf);
+    }
+}
+
+#[test]
+fn edited_payload() {
+    let mut tag = FlvTag::new_video(avc_video(AvcVideoPacket::new_nalu(vec![0; 8], 0)), 0);
+
+    if let FlvTagData::Video(video) = &mut tag.data
+        && let VideoData::Avc(avc) = &mut video.video_data
+    {
+        avc.data = payload(&[0; 300]);
+    }
+
+    assert_encoded_len!(tag);
+    assert_eq!(tag.data_size(), 1 + 4 + 300);
+}
+
+#[test]
+fn sample_file() {
+    let reader = FlvReader::new(SAMPLE).unwrap();
+    let mut encoded = SAMPLE[..13].to_vec();
+
+    assert_encoded_len!(reader.header());
+
+    for tag in reader {
+        let tag = tag.unwrap();
+        let start = encoded.len();
+
+        tag.encode(&mut encoded).unwrap();
+
+        assert_eq!(tag.encoded_len(), encoded.len() - start);
+    }
+
+    assert_eq!(encoded, SAMPLE);
+}
+
+#[test]
+fn unknown_tag_type_byte() {
+    // unknown types, with the Filter bit, and known types with the reserved bits set
+    for tag_type in [0x0F, 0x2F, 0xC9, 0x48] {
+        let mut input = vec![tag_type, 0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 1, 2, 3];
+        input.extend_from_slice(&14_u32.to_be_bytes());
+
+        let tag = FlvTag::decode(&mut &input[..]).unwrap();
+        let (tag_ref, _) = FlvTagRef::parse(&input).unwrap();
+
+        assert_eq!(tag.tag_type(), FlvTagType::Unknown(tag_type));
+        assert_eq!(tag_ref.tag_type(), tag.tag_type());
+        assert!(!tag.is_filtered());
+
+        let mut encoded = Vec::new();
+        tag.encode(&mut encoded).unwrap();
+        assert_eq!(encoded, input);
+
+        // the writer encodes the same bytes as `FlvTag::encode`
+        let header = FlvHeader::new(HeaderFlags::empty());
+        let mut writer = FlvWriter::new(Vec::new(), &header).unwrap();
+        writer.write_tag(&tag).unwrap();
+
+        assert_eq!(writer.into_inner()[13..], input);
+    }
+}
@@ -0,0 +1,107 @@
+use rflv::v1::{
+    encryption::{EncryptionTagHeader, FilterParams},
+    tag::{FlvTag, FlvTagData, FlvTagRef, FlvTagType},
+    video::VideoData,
+};
+
+const IV: [u8; 16] = [0xA5; 16];
+
+/// Filtered tag of `tag_type` with `header` (the audio/video tag header), the encryption header
+/// of `filter` and `body`
+fn filtered_tag(tag_type: u8, header: &[u8], filter: &[u8], body: &[u8]) -> Vec<u8> {
+    let data_size = (header.len() + filter.len() + body.len()) as u32;
+
+    // Filter bit set
+    let mut tag = vec![0x20 | tag_type];
+    tag.extend_from_slice(&data_size.to_be_bytes()[1..]);
+    tag.extend_from_slice(&[0; 7]);
+    tag.extend_from_slice(header);
+    tag.extend_from_slice(filter);
+    tag.extend_from_slice(body);
+    tag.extend_from_slice(&(11 + data_size).to_be_bytes());
+
+    tag
+}
+
+/// NumFilters, FilterName and Length of the params
+fn filter(name: &str, params: &[u8]) -> Vec<u8> {
+    let mut filter = vec![1];
+    filter.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    filter.extend_from_slice(name.as_bytes());
+    filter.extend_from_slice(&(params.len() as u32).to_be_bytes()[1..]);
+    filter.extend_from_slice(params);
+
+    filter
+}
+
+#[test]
+fn filtered_video_tag() {
+    let body = [0xEE; 32];
+    let input = filtered_tag(9, &[0x17, 1, 0, 0, 0], &filter("Encryption", &IV), &body);
+
+    let tag = FlvTag::decode(&mut &input[..]).unwrap();
+
+    assert!(tag.is_filtered());
+    assert_eq!(
+        tag.encryption,
+        Some(EncryptionTagHeader::new(FilterParams::Encryption {
+            iv: IV
+        }))
+    );
+
+    // the encryption header is not part of the payload
+    let FlvTagData::Video(video) = &tag.data else {
+        panic!("not a video tag");
+    };
+    let VideoData::Avc(avc) = &video.video_data else {
+        panic!("not an AVC packet");
+    };
+    assert_eq!(avc.data[..], body);
+
+    let (tag_ref, rest) = FlvTagRef::parse(&input).unwrap();
+    assert!(rest.is_empty());
+    assert_eq!(tag_ref.encryption, tag.encryption);
+
+    let mut encoded = Vec::new();
+    tag.encode(&mut encoded).unwrap();
+    assert_eq!(encoded, input);
+}
+
+#[test]
+fn selective_encryption() {
+    let mut params = vec![0x80];
+    params.extend_from_slice(&IV);
+
+    let cases = [
+        (params, Some(IV)),
+        // EncryptedAU unset, the access unit is clear
+        (vec![0], None),
+    ];
+
+    for (params, iv) in cases {
+        let input = filtered_tag(8, &[0xAF, 1], &filter("SE", &params), &[0xEE; 16]);
+        let tag = FlvTag::decode(&mut &input[..]).unwrap();
+
+        assert_eq!(
+            tag.encryption.unwrap().filter,
+            FilterParams::SelectiveEncryption { iv }
+        );
+    }
+}
+
+#[test]
+fn filtered_script_and_unknown_filter() {
+    let input = filtered_tag(18, &[], &filter("Custom", &[1, 2, 3]), &[0xEE; 16]);
+    let tag = FlvTag::decode(&mut &input[..]).unwrap();
+
+    assert_eq!(tag.data.tag_type(), FlvTagType::ScriptData);
+    assert!(matches!(&tag.data, FlvTagData::EncryptedScript(body) if body[..] == [0xEE; 16]));
+    assert!(matches!(
+        &tag.encryption.as_ref().unwrap().filter,
+        FilterParams::Other { name, params } if name == "Custom" && params[..] == [1, 2, 3]
+    ));
+
+    let mut encoded = Vec::new();
+    tag.encode(&mut encoded).unwrap();
+    assert_eq!(encoded, input);
+}
@@ -0,0 +1,97 @@
+use std::ops::Range;
+
+use rflv::{
+    demux::FlvEvent,
+    file::FlvFile,
+    reader::FlvLenientReader,
+    v1::tag::{FlvTag, FlvTagRefIter},
+};
+
+const SAMPLE: &[u8] = include_bytes!("../example/file.flv");
+const FIRST_TAG: usize = 13;
+
+/// Ranges of the tags of the sample
+fn tag_ranges() -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut iter = FlvTagRefIter::new(&SAMPLE[FIRST_TAG..]);
+    let mut start = FIRST_TAG;
+
+    while iter.next().is_some() {
+        let end = SAMPLE.len() - iter.remaining().len();
+
+        ranges.push(start..end);
+        start = end;
+    }
+
+    ranges
+}
+
+/// The tags encode to the sample tags of `expected`
+fn assert_tags(tags: &[FlvTag], expected: &[Range<usize>]) {
+    assert_eq!(tags.len(), expected.len());
+
+    for (tag, range) in tags.iter().zip(expected) {
+        let mut encoded = Vec::new();
+        tag.encode(&mut encoded).unwrap();
+
+        assert_eq!(encoded, SAMPLE[range.clone()], "tag at {}", range.start);
+    }
+}
+
+/// Only `expected` was skipped
+fn assert_skipped(skipped: &[Range<u64>], expected: Range<usize>) {
+    assert_eq!(skipped.len(), 1, "{skipped:?}");
+    assert_eq!(skipped[0], expected.start as u64..expected.end as u64);
+}
+
+/// Tags and skipped ranges read with `FlvLenientReader`
+fn read_lenient(input: &[u8]) -> (Vec<FlvTag>, Vec<Range<u64>>) {
+    let mut tags = Vec::new();
+    let mut skipped = Vec::new();
+
+    for event in FlvLenientReader::new(input).unwrap() {
+        match event.unwrap() {
+            FlvEvent::Tag(tag) => tags.push(tag),
+            FlvEvent::Skipped(range) => skipped.push(range),
+            FlvEvent::Header(_) => panic!("header after the first event"),
+        }
+    }
+
+    (tags, skipped)
+}
+
+#[test]
+fn corrupt_tag_is_skipped() {
+    let ranges = tag_ranges();
+    let corrupt = ranges[2].clone();
+
+    // the PreviousTagSize of the third tag doesn't match its size
+    let mut input = SAMPLE.to_vec();
+    input[corrupt.end - 1] ^= 0xFF;
+
+    let expected_tags = [&ranges[..2], &ranges[3..]].concat();
+
+    let (file, skipped) = FlvFile::decode_lenient(&mut &input[..]).unwrap();
+
+    assert_tags(&file.tags, &expected_tags);
+    assert_skipped(&skipped, corrupt.clone());
+
+    let (tags, skipped) = read_lenient(&input);
+
+    assert_tags(&tags, &expected_tags);
+    assert_skipped(&skipped, corrupt);
+}
+
+#[test]
+fn garbage_between_tags_is_skipped() {
+    let ranges = tag_ranges();
+    let at = ranges[1].start;
+
+    let mut input = SAMPLE.to_vec();
+    input.splice(at..at, [0xFF; 5]);
+
+    let (file, skipped) = FlvFile::decode_lenient(&mut &input[..]).unwrap();
+
+    assert_tags(&file.tags, &ranges);
+    assert_skipped(&skipped, at..at + 5);
+}
@@ -0,0 +1,78 @@
+use rflv::{
+    error::FlvError,
+    v1::{header::FlvHeader, limits::DecodeLimits, tag::FlvTag},
+};
+
+/// Tag header with `data_size` followed by `data` and the PreviousTagSize of the header
+fn tag(tag_type: u8, data_size: u8, data: &[u8]) -> Vec<u8> {
+    let mut tag = vec![tag_type, 0, 0, data_size, 0, 0, 0, 0, 0, 0, 0];
+
+    tag.extend_from_slice(data);
+    tag.extend_from_slice(&(11 + data_size as u32).to_be_bytes());
+
+    tag
+}
+
+#[test]
+fn undersized_tags() {
+    // AAC with only the SoundInfo byte, AVC with only FrameType/CodecId and AVCPacketType
+    let cases = [
+        tag(8, 0, &[]),
+        tag(8, 1, &[0xAF]),
+        tag(9, 0, &[]),
+        tag(9, 2, &[0x17, 1]),
+    ];
+
+    // the size is checked before the headers are read, the PreviousTagSize is never read as data
+    for input in cases {
+        let mut rest = &input[..];
+        let e = FlvTag::decode(&mut rest).unwrap_err();
+
+        assert!(matches!(e.root(), FlvError::DataSizeTooSmall(_)), "{e}");
+        assert!(rest.len() >= 4, "read past the data: {input:?}");
+    }
+}
+
+/// FLV header whose data_offset claims about 4 GiB of extra bytes
+const HUGE_HEADER: &[u8] = &[b'F', b'L', b'V', 1, 5, 0xFF, 0xFF, 0xFF, 0xFF];
+
+fn small_limits() -> DecodeLimits {
+    DecodeLimits {
+        max_total_alloc: 1024,
+        ..DecodeLimits::default()
+    }
+}
+
+#[test]
+fn header_extra_over_limits() {
+    let e = FlvHeader::decode_with_limits(&mut &HUGE_HEADER[..], &small_limits()).unwrap_err();
+
+    assert!(
+        matches!(e.root(), FlvError::LimitExceeded("max_total_alloc")),
+        "{e}"
+    );
+}
+
+#[cfg(feature = "file")]
+#[test]
+fn reader_and_scanner_check_header_limits() {
+    use std::io::Cursor;
+
+    use rflv::{reader::FlvReader, scan::FlvScanner};
+
+    let Err(e) = FlvReader::new_with_limits(HUGE_HEADER, small_limits()) else {
+        panic!("the reader accepted the header");
+    };
+    assert!(
+        matches!(e.root(), FlvError::LimitExceeded("max_total_alloc")),
+        "{e}"
+    );
+
+    let Err(e) = FlvScanner::new_with_limits(Cursor::new(HUGE_HEADER), small_limits()) else {
+        panic!("the scanner accepted the header");
+    };
+    assert!(
+        matches!(e.root(), FlvError::LimitExceeded("max_total_alloc")),
+        "{e}"
+    );
+}
@@ -0,0 +1,181 @@
+use rflv::v1::{
+    metadata::{MetadataError, OnMetaData},
+    script::{
+        Amf0Bool, Amf0DataObjectProp, Amf0EcmaArray, Amf0Key, Amf0Number, Amf0Object,
+        Amf0StrictArray, Amf0String, Amf0Value, FlvScriptTag,
+    },
+};
+
+fn prop(name: &str, value: Amf0Value) -> Amf0DataObjectProp {
+    Amf0DataObjectProp {
+        name: Amf0Key::new(name.to_string()).unwrap(),
+        value,
+    }
+}
+
+fn number(n: f64) -> Amf0Value {
+    Amf0Value::Number(Amf0Number::new(n))
+}
+
+fn string(content: &str) -> Amf0Value {
+    Amf0Value::String(Amf0String::new(content.to_string()).unwrap())
+}
+
+fn numbers(numbers: &[f64]) -> Amf0Value {
+    Amf0Value::StrictArray(Amf0StrictArray::new(
+        numbers.iter().copied().map(number).collect(),
+    ))
+}
+
+/// Props as encoded in an ECMA array
+fn encode(props: &[Amf0DataObjectProp]) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    Amf0EcmaArray::new(props.to_vec()).encode(&mut buf).unwrap();
+
+    buf
+}
+
+/// Standard keys out of their usual order, between other keys, with a null one and an extra
+/// member inside `keyframes`
+fn props() -> Vec<Amf0DataObjectProp> {
+    let keyframes = Amf0Object::new(vec![
+        prop("times", numbers(&[0.0, 2.0])),
+        prop("spacing", number(2.0)),
+        prop("filepositions", numbers(&[13.0, 4096.0])),
+    ]);
+
+    vec![
+        prop("encoder", string("rflv")),
+        prop("custom", string("value")),
+        prop("width", Amf0Value::Null),
+        prop("duration", number(4.0)),
+        prop("keyframes", Amf0Value::Object(keyframes)),
+        prop("hasVideo", Amf0Value::Bool(Amf0Bool::new(true))),
+    ]
+}
+
+#[test]
+fn props_round_trip() {
+    let props = props();
+    let mut metadata = OnMetaData::from_props(&props).unwrap();
+
+    assert_eq!(metadata.width, None);
+    assert_eq!(metadata.duration, Some(4.0));
+    assert_eq!(metadata.keyframes.as_ref().unwrap().times, [0.0, 2.0]);
+    assert_eq!(encode(&metadata.to_props().unwrap()), encode(&props));
+
+    // an edited field keeps its place, a field that is set replaces the null
+    metadata.duration = Some(8.0);
+    metadata.width = Some(1280.0);
+
+    let mut edited = props;
+    edited[2] = prop("width", number(1280.0));
+    edited[3] = prop("duration", number(8.0));
+
+    assert_eq!(encode(&metadata.to_props().unwrap()), encode(&edited));
+}
+
+fn encode_script(script: &FlvScriptTag) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    script.encode(&mut buf).unwrap();
+
+    buf
+}
+
+#[test]
+fn extra_keys() {
+    let mut metadata = OnMetaData::from_props(&props()).unwrap();
+
+    assert!(
+        matches!(metadata.get_extra("custom"), Some(Amf0Value::String(s)) if s.content == "value")
+    );
+    assert!(matches!(metadata.get_extra("width"), Some(Amf0Value::Null)));
+    // a standard key that is set is only in its field
+    assert!(metadata.get_extra("duration").is_none());
+
+    metadata.set_extra("custom", number(1.0)).unwrap();
+    metadata.set_extra("other", number(2.0)).unwrap();
+
+    let props = metadata.to_props().unwrap();
+
+    assert_eq!(props[1].name.key, "custom");
+    assert!(matches!(&props[1].value, Amf0Value::Number(n) if n.value() == 1.0));
+    assert_eq!(props.last().unwrap().name.key, "other");
+}
+
+#[test]
+fn set_data_frame_round_trip() {
+    let script = FlvScriptTag::new(
+        "@setDataFrame".to_string(),
+        vec![
+            string("onMetaData"),
+            Amf0Value::EcmaArray(Amf0EcmaArray::new(props())),
+        ],
+    )
+    .unwrap();
+
+    let metadata = OnMetaData::try_from(&script).unwrap();
+
+    assert_eq!(metadata.duration, Some(4.0));
+    assert_eq!(
+        encode_script(&FlvScriptTag::try_from(&metadata).unwrap()),
+        encode_script(&script)
+    );
+
+    // props on their own are written as onMetaData
+    let metadata = OnMetaData::from_props(&props()).unwrap();
+    let script = FlvScriptTag::try_from(&metadata).unwrap();
+
+    assert_eq!(script.name.content, "onMetaData");
+    assert_eq!(script.values.len(), 1);
+}
+
+#[test]
+fn keyframes_in_ecma_array() {
+    let mut props = props();
+    let Amf0Value::Object(keyframes) = &props[4].value else {
+        panic!("keyframes is not an object");
+    };
+    props[4] = prop(
+        "keyframes",
+        Amf0Value::EcmaArray(Amf0EcmaArray::new(keyframes.props.clone())),
+    );
+
+    let metadata = OnMetaData::from_props(&props).unwrap();
+
+    assert_eq!(
+        metadata.keyframes.as_ref().unwrap().filepositions,
+        [13, 4096]
+    );
+    assert_eq!(encode(&metadata.to_props().unwrap()), encode(&props));
+}
+
+#[test]
+fn long_encoder_string() {
+    let encoder = "a".repeat(u16::MAX as usize + 1);
+    let mut metadata = OnMetaData::default();
+    metadata.encoder = Some(encoder.clone());
+
+    let props = metadata.to_props().unwrap();
+
+    assert!(matches!(&props[0].value, Amf0Value::LongString(s) if s.content == encoder));
+    assert_eq!(
+        OnMetaData::from_props(&props).unwrap().encoder,
+        Some(encoder)
+    );
+}
+
+#[test]
+fn mistyped_standard_key() {
+    let mut props = props();
+    props[3] = prop("duration", string("4"));
+
+    let e = OnMetaData::from_props(&props).unwrap_err();
+
+    assert!(
+        matches!(&e, MetadataError::InvalidType { key, .. } if key == "duration"),
+        "{e}"
+    );
+}
@@ -0,0 +1,68 @@
+use std::io::Cursor;
+
+use rflv::{
+    error::FlvError,
+    reader::FlvReader,
+    scan::FlvScanner,
+    v1::{
+        tag::{FlvTag, FlvTagData},
+        video::FrameType,
+    },
+};
+
+const SAMPLE: &[u8] = include_bytes!("../example/file.flv");
+const FIRST_TAG: usize = 13;
+
+#[test]
+fn offsets_of_sample_file() {
+    let scanner = FlvScanner::new(Cursor::new(SAMPLE)).unwrap();
+    let infos = scanner.collect::<Result<Vec<_>, _>>().unwrap();
+
+    assert!(infos.iter().any(|info| info.keyframe));
+    assert_eq!(infos[0].offset, FIRST_TAG as u64);
+
+    // every offset is where the previous tag ends, the last tag ends with the file
+    for pair in infos.windows(2) {
+        assert_eq!(pair[1].offset, pair[0].offset + pair[0].encoded_len());
+    }
+
+    let last = infos.last().unwrap();
+    assert_eq!(last.offset + last.encoded_len(), SAMPLE.len() as u64);
+
+    // and points to a tag that decodes to what the scanner saw
+    for info in &infos {
+        let tag = FlvTag::decode(&mut &SAMPLE[info.offset as usize..]).unwrap();
+
+        assert_eq!(tag.tag_type(), info.tag_type);
+        assert_eq!(tag.timestamp, info.timestamp);
+        assert_eq!(tag.data_size(), info.data_size as usize);
+
+        let keyframe = matches!(
+            &tag.data,
+            FlvTagData::Video(video)
+                if video.frame_type == FrameType::Keyframe && !video.is_sequence_header()
+        );
+        assert_eq!(keyframe, info.keyframe, "tag at {}", info.offset);
+    }
+}
+
+#[test]
+fn truncated_sample_file() {
+    let end = SAMPLE.len() - 1;
+    let scanner = FlvScanner::new(Cursor::new(&SAMPLE[..end])).unwrap();
+
+    let e = scanner.last().unwrap().unwrap_err();
+
+    assert!(matches!(e.root(), FlvError::Truncated { .. }), "{e}");
+}
+
+#[test]
+fn truncated_header() {
+    for end in 0..FIRST_TAG {
+        let e = FlvScanner::new(Cursor::new(&SAMPLE[..end])).err().unwrap();
+        assert!(matches!(e.root(), FlvError::Truncated { .. }), "{e}");
+
+        let e = FlvReader::new(&SAMPLE[..end]).err().unwrap();
+        assert!(matches!(e.root(), FlvError::Truncated { .. }), "{e}");
+    }
+}
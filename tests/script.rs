@@ -0,0 +1,120 @@
+use std::ops::Range;
+
+use rflv::v1::script::{Amf0EcmaArray, Amf0Error, Amf0Value, FlvScriptTag};
+
+/// Count of the ECMA array after the `onMetaData` name
+const COUNT: Range<usize> = 14..18;
+
+/// `onMetaData` name followed by `values`
+fn script(values: &[u8]) -> Vec<u8> {
+    let mut data = vec![0x02, 0x00, 0x0A];
+
+    data.extend_from_slice(b"onMetaData");
+    data.extend_from_slice(values);
+
+    data
+}
+
+/// ECMA array with a count of 0 and a `duration` of 12.5
+fn ecma_array() -> Vec<u8> {
+    let mut array = vec![0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08];
+
+    array.extend_from_slice(b"duration");
+    array.push(0x00);
+    array.extend_from_slice(&12.5_f64.to_be_bytes());
+    array.extend_from_slice(&[0x00, 0x00, 0x09]);
+
+    array
+}
+
+fn decode(data: &[u8]) -> Result<FlvScriptTag, Amf0Error> {
+    FlvScriptTag::decode(&mut &data[..], data.len())
+}
+
+fn encode(script: &FlvScriptTag) -> Vec<u8> {
+    let mut buf = Vec::new();
+
+    script.encode(&mut buf).unwrap();
+
+    buf
+}
+
+#[test]
+fn ecma_array_count_round_trip() {
+    let data = script(&ecma_array());
+    let script = decode(&data).unwrap();
+
+    let [Amf0Value::EcmaArray(array)] = &script.values[..] else {
+        panic!("not one ECMA array: {script:?}");
+    };
+
+    // the wrong count is kept, `new` counts the props
+    assert_eq!(array.count(), 0);
+    assert_eq!(array.props.len(), 1);
+    assert_eq!(Amf0EcmaArray::new(array.props.clone()).count(), 1);
+
+    assert_eq!(encode(&script), data);
+
+    // once props are added or removed the count is the number of props
+    let mut edited = script.clone();
+    let props = edited.props_mut().unwrap();
+    props.push(props[0].clone());
+
+    assert_eq!(encode(&edited)[COUNT], 2_u32.to_be_bytes());
+
+    edited.props_mut().unwrap().truncate(1);
+
+    assert_eq!(encode(&edited), data);
+}
+
+#[test]
+fn known_trailing_bytes() {
+    let stray_object_end = [0x00, 0x00, 0x09];
+    let padded_object_end = [
+        0x00, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let padding = [0x00; 8];
+
+    for trailing in [&stray_object_end[..], &padded_object_end, &padding] {
+        let data = script(&[&ecma_array()[..], trailing].concat());
+        let script = decode(&data).unwrap();
+
+        assert_eq!(script.values.len(), 1);
+        assert_eq!(&script.trailing[..], trailing);
+        assert_eq!(encode(&script), data);
+    }
+}
+
+#[test]
+fn unknown_trailing_bytes() {
+    let not_a_value = [0x09, 0xFF, 0x01];
+    let after_object_end = [0x00, 0x00, 0x09, 0x01];
+
+    let e = decode(&script(&[&ecma_array()[..], &not_a_value].concat())).unwrap_err();
+
+    assert!(matches!(e, Amf0Error::InvalidId), "{e}");
+
+    // a stray object end is only known junk at the end of the data
+    assert!(decode(&script(&[&ecma_array()[..], &after_object_end].concat())).is_err());
+}
+
+#[test]
+fn errors_inside_values() {
+    // ECMA array with an empty key not followed by the object end marker, and with a prop value
+    // of an unknown marker
+    let props = ecma_array()[..ecma_array().len() - 3].to_vec();
+
+    let bad_object_end = [&props[..], &[0x00, 0x00, 0x08]].concat();
+    let invalid_prop = [&props[..], &[0x00, 0x01, b'x', 0x04, 0x00, 0x00, 0x09]].concat();
+
+    for values in [bad_object_end, invalid_prop] {
+        let e = decode(&script(&values)).unwrap_err();
+
+        assert!(matches!(e, Amf0Error::InvalidId), "{e}");
+    }
+
+    // a string longer than the data
+    let e = decode(&script(&[0x02, 0x00, 0x10, b'a'])).unwrap_err();
+
+    assert!(matches!(e, Amf0Error::Truncated { .. }), "{e}");
+}
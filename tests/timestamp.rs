@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use rflv::v1::timestamp::{FlvTimestamp, TimestampUnwrapper};
+
+const WRAP: u64 = 1 << 32;
+
+#[test]
+fn unwrap_across_rollover() {
+    let mut unwrapper = TimestampUnwrapper::new();
+
+    let before = FlvTimestamp::from_millis(u32::MAX - 10);
+    let after = before + Duration::from_millis(30);
+
+    assert_eq!(after.as_millis(), 19);
+
+    assert_eq!(unwrapper.unwrap(before), Duration::from_millis(WRAP - 11));
+    assert_eq!(unwrapper.unwrap(after), Duration::from_millis(WRAP + 19));
+
+    // a late tag from before the rollover keeps its time and doesn't count another rollover
+    assert_eq!(
+        unwrapper.unwrap(FlvTimestamp::from_millis(u32::MAX - 5)),
+        Duration::from_millis(WRAP - 6)
+    );
+    assert_eq!(
+        unwrapper.unwrap(FlvTimestamp::from_millis(40)),
+        Duration::from_millis(WRAP + 40)
+    );
+
+    // the second rollover, after the timestamps went through the whole range again
+    for millis in [1 << 31, u32::MAX] {
+        assert_eq!(
+            unwrapper.unwrap(FlvTimestamp::from_millis(millis)),
+            Duration::from_millis(WRAP + millis as u64)
+        );
+    }
+
+    assert_eq!(
+        unwrapper.unwrap(FlvTimestamp::from_millis(5)),
+        Duration::from_millis(2 * WRAP + 5)
+    );
+}
+
+#[test]
+fn duration_conversions() {
+    let timestamp = FlvTimestamp::from_parts(0x12_3456, 0x78);
+
+    assert_eq!(timestamp.as_millis(), 0x7812_3456);
+    assert_eq!(timestamp.to_duration(), Duration::from_millis(0x7812_3456));
+    assert_eq!(Duration::from(timestamp), timestamp.to_duration());
+
+    // sub-millisecond precision is truncated
+    assert_eq!(
+        FlvTimestamp::try_from(Duration::from_micros(1_999)),
+        Ok(FlvTimestamp::from_millis(1))
+    );
+    assert_eq!(
+        FlvTimestamp::try_from(Duration::from_millis(u32::MAX as u64)),
+        Ok(FlvTimestamp::from_millis(u32::MAX))
+    );
+    assert!(FlvTimestamp::try_from(Duration::from_millis(WRAP)).is_err());
+}
+
+#[test]
+fn arithmetic_at_the_end_of_the_range() {
+    let max = FlvTimestamp::from_millis(u32::MAX);
+    let one = Duration::from_millis(1);
+
+    assert_eq!(max.checked_add(one), None);
+    assert_eq!(max.checked_add(Duration::ZERO), Some(max));
+    assert_eq!(FlvTimestamp::ZERO.checked_sub(one), None);
+    assert_eq!(
+        max.checked_sub(one),
+        Some(FlvTimestamp::from_millis(u32::MAX - 1))
+    );
+    // a duration that doesn't fit in 32 bits of milliseconds
+    assert_eq!(
+        FlvTimestamp::ZERO.checked_add(Duration::from_millis(WRAP)),
+        None
+    );
+    assert_eq!(max.checked_sub(Duration::from_millis(WRAP)), None);
+
+    assert_eq!(
+        max.duration_since(FlvTimestamp::ZERO),
+        Duration::from_millis(u32::MAX as u64)
+    );
+    assert_eq!(FlvTimestamp::ZERO.duration_since(max), Duration::ZERO);
+
+    // the operators wrap around like the field
+    assert_eq!(max + one, FlvTimestamp::ZERO);
+    assert_eq!(FlvTimestamp::ZERO - one, max);
+    assert_eq!(
+        max + Duration::from_millis(WRAP + 2),
+        FlvTimestamp::from_millis(1)
+    );
+
+    let mut timestamp = max;
+    timestamp += Duration::from_millis(3);
+    assert_eq!(timestamp, FlvTimestamp::from_millis(2));
+    timestamp -= Duration::from_millis(3);
+    assert_eq!(timestamp, max);
+}
@@ -0,0 +1,159 @@
+use std::io::Cursor;
+
+use rflv::{
+    file::FlvFileBuilder,
+    reader::FlvReader,
+    v1::{
+        header::{FlvHeader, HeaderFlags},
+        metadata::OnMetaData,
+        script::{Amf0DataObjectProp, Amf0Key, Amf0String, Amf0Value},
+        tag::{FlvTag, FlvTagData},
+        timestamp::FlvTimestamp,
+        video::{AvcVideoPacket, CodecId, FlvVideoData, FrameType, VideoData},
+    },
+    writer::FlvWriter,
+};
+
+fn video(frame_type: FrameType, packet: AvcVideoPacket) -> FlvVideoData {
+    FlvVideoData {
+        frame_type,
+        codec: CodecId::Avc,
+        video_data: VideoData::Avc(packet),
+    }
+}
+
+fn keyframe() -> FlvVideoData {
+    video(
+        FrameType::Keyframe,
+        AvcVideoPacket::new_nalu(vec![0; 32], 0),
+    )
+}
+
+#[test]
+fn metadata_is_patched_on_finish() {
+    let encoder = Amf0DataObjectProp {
+        name: Amf0Key::new("encoder".to_string()).unwrap(),
+        value: Amf0Value::String(Amf0String::new("rflv".to_string()).unwrap()),
+    };
+
+    let mut writer =
+        FlvWriter::new(Cursor::new(Vec::new()), &FlvHeader::new(HeaderFlags::VIDEO)).unwrap();
+
+    writer.write_metadata(vec![encoder], 2).unwrap();
+
+    // the sequence header is not indexed, and keyframes past `max_keyframes` are not either
+    let sequence_header = AvcVideoPacket::new_sequence_header(vec![1, 2, 3]);
+    writer
+        .write_video(video(FrameType::Keyframe, sequence_header), 0)
+        .unwrap();
+
+    let mut keyframes = Vec::new();
+
+    keyframes.push(writer.position());
+    writer.write_video(keyframe(), 0).unwrap();
+
+    let inter = AvcVideoPacket::new_nalu(vec![0; 16], 0);
+    writer
+        .write_video(video(FrameType::InterFrame, inter), 40)
+        .unwrap();
+
+    keyframes.push(writer.position());
+    writer.write_video(keyframe(), 1000).unwrap();
+
+    writer.write_video(keyframe(), 2500).unwrap();
+
+    let file = writer.finish().unwrap().into_inner();
+
+    let mut reader = FlvReader::new(&file[..]).unwrap();
+    let FlvTagData::Script(script) = reader.next().unwrap().unwrap().data else {
+        panic!("the first tag is not the metadata");
+    };
+
+    let metadata = OnMetaData::try_from(&script).unwrap();
+
+    assert_eq!(metadata.encoder.as_deref(), Some("rflv"));
+    assert_eq!(metadata.duration, Some(2.5));
+    assert_eq!(metadata.last_timestamp, Some(2.5));
+    assert_eq!(metadata.file_size, Some(file.len() as f64));
+
+    let index = metadata.keyframes.unwrap();
+
+    assert_eq!(index.filepositions, keyframes);
+    assert_eq!(index.times, [0.0, 1.0]);
+
+    // the patched metadata has the size that was reserved, the offsets still point to the tags
+    assert_eq!(reader.count(), 5);
+
+    for position in index.filepositions {
+        let tag = FlvTag::decode(&mut &file[position as usize..]).unwrap();
+
+        assert!(matches!(
+            tag.data,
+            FlvTagData::Video(video) if video.frame_type == FrameType::Keyframe
+        ));
+    }
+}
+
+#[test]
+fn builder_orders_across_rollover() {
+    let before = u32::MAX - 40;
+
+    let mut builder = FlvFileBuilder::new();
+
+    builder
+        .add_video(keyframe(), before)
+        .add_video(keyframe(), 10u32)
+        .add_video(keyframe(), before + 20)
+        .add_video(
+            video(
+                FrameType::Keyframe,
+                AvcVideoPacket::new_sequence_header(vec![1, 2, 3]),
+            ),
+            30u32,
+        );
+
+    let file = builder.build();
+
+    let timestamps: Vec<_> = file.tags.iter().map(|tag| tag.timestamp).collect();
+
+    // the sequence header moves to the start with the earliest timestamp, the tag after the
+    // rollover stays after the ones before it
+    assert_eq!(
+        timestamps,
+        [before, before, before + 20, 10].map(FlvTimestamp::from_millis)
+    );
+    assert!(matches!(
+        &file.tags[0].data,
+        FlvTagData::Video(video) if video.is_sequence_header()
+    ));
+}
+
+#[test]
+fn metadata_keeps_padding_prop_of_the_caller() {
+    let padding = Amf0DataObjectProp {
+        name: Amf0Key::new("padding".to_string()).unwrap(),
+        value: Amf0Value::String(Amf0String::new("mine".to_string()).unwrap()),
+    };
+
+    let mut writer =
+        FlvWriter::new(Cursor::new(Vec::new()), &FlvHeader::new(HeaderFlags::VIDEO)).unwrap();
+
+    writer.write_metadata(vec![padding], 1).unwrap();
+    writer.write_video(keyframe(), 0).unwrap();
+
+    let file = writer.finish().unwrap().into_inner();
+
+    let mut reader = FlvReader::new(&file[..]).unwrap();
+    let FlvTagData::Script(script) = reader.next().unwrap().unwrap().data else {
+        panic!("the first tag is not the metadata");
+    };
+
+    // the prop of the caller comes first, the padding of the writer after the other props
+    let props = script.props().unwrap();
+
+    assert!(matches!(
+        &props[0].value,
+        Amf0Value::String(s) if props[0].name.key == "padding" && s.content == "mine"
+    ));
+    assert!(props.last().unwrap().name.key == "padding");
+}